@@ -1,14 +1,280 @@
+/// Asserts that `res` is equal to at least one of the expected values.
+/// On failure every alternative is listed, and the closest one is shown with a diff: field by field for `Packet`s
+/// and `DroneEvent`s, line by line of the `Debug` output for other types.
+///
+/// ```ignore
+/// assert_matches_any!(res, flood_res_d12, flood_res_d13);
+/// ```
 #[macro_export]
 macro_rules! assert_matches_any {
-    ($res:expr, $expected1:expr, $expected2:expr) => {
+    ($res:expr, $($expected:expr),+ $(,)?) => {
+        if let Err(msg) = $crate::diff::match_any(&$res, &[$(&$expected),+], |actual, expected| {
+            #[allow(unused_imports)]
+            use $crate::diff::{DebugCompare as _, FieldCompare as _};
+            (&$crate::diff::Pair(actual, expected)).compare()
+        }) {
+            panic!("{}", msg);
+        }
+    };
+}
+
+/// Asserts that `res` matches the pattern, `matches!`-style: alternatives with `|` and an optional `if` guard.
+///
+/// ```ignore
+/// assert_matches_pattern!(
+///     res.pack_type,
+///     PacketType::Nack(Nack { nack_type: NackType::ErrorInRouting(_), .. })
+/// );
+/// ```
+#[macro_export]
+macro_rules! assert_matches_pattern {
+    ($res:expr, $($pattern:pat_param)|+ $(if $guard:expr)? $(,)?) => {
+        match $res {
+            $($pattern)|+ $(if $guard)? => {}
+            ref res => panic!(
+                "Assertion failed: `res` does not match the expected pattern.\n\
+                 Actual: `{:?}`\n\
+                 Expected: `{}`",
+                res,
+                stringify!($($pattern)|+ $(if $guard)?)
+            ),
+        }
+    };
+}
+
+/// Same as `assert_eq!` for two `Packet`s, but a failure reports the differing fields one by one
+/// and leaves out the fragment payloads.
+#[macro_export]
+macro_rules! assert_packet_eq {
+    ($actual:expr, $expected:expr $(,)?) => {
+        if let Some(msg) = $crate::packet_diff::packet_mismatch(&$actual, &$expected) {
+            panic!("{}", msg);
+        }
+    };
+}
+
+/// Same as [`assert_packet_eq!`] for two `DroneEvent`s.
+#[macro_export]
+macro_rules! assert_event_eq {
+    ($actual:expr, $expected:expr $(,)?) => {
+        if let Some(msg) = $crate::packet_diff::event_mismatch(&$actual, &$expected) {
+            panic!("{}", msg);
+        }
+    };
+}
+
+/// Expands every `generic_*` test of this crate into a separate `#[test]` item for the drone type `$drone`.
+/// Tests listed in `skip` are expanded with `#[ignore]`, so that `cargo test` reports them as ignored; the names are
+/// checked against the suite.
+/// With `seeded`, the tests requiring [`SeededDrone`](crate::SeededDrone) are expanded too.
+///
+/// ```ignore
+/// mod conformance {
+///     rusteze_tests::conformance_suite!(my_drone::MyDrone);
+/// }
+///
+/// mod conformance_with_known_gaps {
+///     rusteze_tests::conformance_suite!(my_drone::MyDrone, skip = [generic_known_flood_req]);
+/// }
+///
+/// mod seeded_conformance {
+///     rusteze_tests::conformance_suite!(my_drone::MyDrone, seeded);
+/// }
+/// ```
+#[macro_export]
+macro_rules! conformance_suite {
+    ($drone:ty) => {
+        $crate::conformance_suite!($drone, skip = []);
+    };
+    ($drone:ty, seeded) => {
+        $crate::conformance_suite!($drone, seeded, skip = []);
+    };
+    ($drone:ty, skip = $skip:tt) => {
+        $crate::__conformance_list!(__conformance_tests { $drone, $skip });
+    };
+    ($drone:ty, seeded, skip = $skip:tt) => {
+        $crate::__conformance_list!(__conformance_tests { $drone, $skip }, seeded);
+    };
+}
+
+/// Calls `$crate::$callback! { $args; module::generic_test, ... }` with every test of the suite.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __conformance_list {
+    ($callback:ident { $($args:tt)* }) => {
+        $crate::__conformance_list!(@list $callback { $($args)* };);
+    };
+    ($callback:ident { $($args:tt)* }, seeded) => {
+        // Every `generic_*` function requiring `SeededDrone` must be listed here
+        $crate::__conformance_list! {
+            @list $callback { $($args)* };
+            pdr_generics::generic_statistical_pdr_seeded,
+            pdr_generics::generic_seeded_pdr_replay,
+        }
+    };
+    (@list $callback:ident { $($args:tt)* }; $($extra:tt)*) => {
+        // Every `generic_*` function of the crate must be listed here, `conformance_list_is_complete` checks it
+        $crate::$callback! {
+            $($args)*;
+            sc_generics::generic_receive_sc_command,
+            sc_generics::generic_handle_crash,
+            sc_generics::generic_add_sender,
+            sc_generics::generic_remove_sender,
+            sc_generics::generic_set_packet_drop_rate,
+            sc_generics::generic_crash_exit,
+            fragment_generics::generic_fragment_forward,
+            fragment_generics::generic_fragment_drop,
+            fragment_generics::generic_chain_fragment_drop,
+            fragment_generics::generic_chain_fragment_ack,
+            fragment_generics::generic_ack_forward,
+            fragment_generics::generic_nack_forward,
+            fragment_generics::generic_destination_is_drone,
+            fragment_generics::generic_error_in_routing,
+            fragment_generics::generic_unexpected_recipient,
+            fragment_generics::generic_unexpected_recipient_chain,
+            flood_generics::generic_new_flood,
+            flood_generics::generic_new_flood_no_initiator,
+            flood_generics::generic_new_flood_neighbours,
+            flood_generics::generic_flood_res_forward,
+            flood_generics::generic_known_flood_req,
+            flood_generics::generic_flood_req_two_initiator,
+            pdr_generics::generic_statistical_pdr,
+            differential_generics::generic_differential_routing,
+            differential_generics::generic_differential_commands,
+            differential_generics::generic_differential_flood,
+            fuzz_generics::generic_fuzz_packets,
+            routing_generics::generic_hop_index_zero,
+            routing_generics::generic_hop_index_past_end,
+            routing_generics::generic_empty_hops,
+            routing_generics::generic_drone_twice_in_route,
+            routing_generics::generic_wrong_previous_hop,
+            payload_generics::generic_payload_integrity,
+            payload_generics::generic_payload_nack_index,
+            message_generics::generic_message_end_to_end,
+            discovery_generics::generic_flood_discovery_ring,
+            discovery_generics::generic_flood_discovery_grid,
+            discovery_generics::generic_flood_discovery_mesh,
+            termination_generics::generic_flood_terminates_triangle,
+            termination_generics::generic_flood_terminates_square,
+            termination_generics::generic_flood_terminates_complete,
+            flood_return_generics::generic_flood_res_multi_hop,
+            flood_return_generics::generic_flood_res_over_crashed,
+            flood_return_generics::generic_flood_res_removed_hop,
+            shortcut_generics::generic_shortcut_ack,
+            shortcut_generics::generic_shortcut_nack,
+            shortcut_generics::generic_shortcut_flood_res,
+            controller_generics::generic_controller_delivers_shortcut,
+            controller_generics::generic_controller_shortcut_mid_route,
+            controller_generics::generic_controller_connect,
+            controller_generics::generic_controller_crash,
+            controller_generics::generic_controller_set_pdr,
+            $($extra)*
+        }
+    };
+}
+
+/// Names of the tests given by `__conformance_list!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __conformance_names {
+    (; $($module:ident :: $test:ident),* $(,)?) => {
+        &[$(stringify!($test)),*]
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __conformance_tests {
+    ($drone:ty, $skip:tt; $($module:ident :: $test:ident),* $(,)?) => {
+        // `$` is passed along so that the helper can declare a macro of its own
+        $crate::__conformance_test_items! {
+            ($) $drone, $skip; $($module :: $test),*
+        }
+
+        #[test]
+        fn conformance_suite_skip_list() {
+            let tests: &[&str] = &[$(stringify!($test)),*];
+            for name in $crate::__skip_list!($skip) {
+                assert!(
+                    tests.contains(name),
+                    "`{}` in the skip list is not a test of the conformance suite",
+                    name
+                );
+            }
+        }
+    };
+}
+
+/// Declares a local macro with an arm for every skipped test, so that skipped tests are expanded with `#[ignore]`.
+/// They are still run by `cargo test -- --ignored`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __conformance_test_items {
+    (($d:tt) $drone:ty, [$($skip:ident),* $(,)?]; $($module:ident :: $test:ident),*) => {
+        macro_rules! __conformance_test {
+            $(
+                ($skip, $d module:ident) => {
+                    #[test]
+                    #[ignore = "skipped by conformance_suite!"]
+                    fn $skip() {
+                        $crate::$d module::$skip::<$drone>();
+                    }
+                };
+            )*
+            ($d test:ident, $d module:ident) => {
+                #[test]
+                fn $d test() {
+                    $crate::$d module::$d test::<$drone>();
+                }
+            };
+        }
+        $(__conformance_test!($test, $module);)*
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __skip_list {
+    ([$($skip:ident),* $(,)?]) => {
+        &[$(stringify!($skip)),*] as &[&str]
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::Path;
+
+    /// A `generic_*` test missing from `__conformance_list!` would never reach the drones using the suite.
+    #[test]
+    fn conformance_list_is_complete() {
+        let listed: &[&str] = crate::__conformance_list!(__conformance_names {}, seeded);
+
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("src/drone");
+        let mut missing = Vec::new();
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if !path.to_string_lossy().ends_with("_generics.rs") {
+                continue;
+            }
+            for line in fs::read_to_string(&path).unwrap().lines() {
+                let Some(rest) = line.strip_prefix("pub fn generic_") else {
+                    continue;
+                };
+                let name: String = rest
+                    .chars()
+                    .take_while(|c| c.is_alphanumeric() || *c == '_')
+                    .collect();
+                let name = format!("generic_{}", name);
+                if !listed.contains(&name.as_str()) {
+                    missing.push(format!("{} ({})", name, path.display()));
+                }
+            }
+        }
         assert!(
-            $res == $expected1 || $res == $expected2,
-            "Assertion failed: `res` does not match any expected values.\n\
-             Actual: `{:?}`\n\
-             Expected: `{:?}`\nOR\n`{:?}`",
-            $res,
-            $expected1,
-            $expected2
+            missing.is_empty(),
+            "Not listed in `__conformance_list!`:\n{}",
+            missing.join("\n")
         );
-    };
+    }
 }
@@ -0,0 +1,117 @@
+use std::thread;
+use wg_internal::controller::DroneEvent;
+use wg_internal::drone::Drone;
+use wg_internal::packet::NackType;
+
+use crate::assert_packet_eq;
+use crate::controller::SimController;
+use crate::fragment_generics::{chain_topology, create_sample_packet, get_ack, get_nack};
+use crate::recorder::{assert_silent, Recorder};
+use crate::timing::{quiet, settle, timeout};
+
+/* THE FOLLOWING TESTS CHECKS IF YOUR DRONE WORKS WITH A SIMULATION CONTROLLER DRIVING THE NETWORK */
+
+/// This function checks if an ACK that cannot reach the client is given to the SC, which delivers it to the client.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21), then C(1) and D(11) are unlinked
+pub fn generic_controller_delivers_shortcut<T: Drone + Send + 'static>() {
+    let mut sc = SimController::new(chain_topology(0.0, 0.0).spawn::<T>());
+    sc.disconnect(1, 11);
+    thread::sleep(settle());
+
+    sc.probe(21).send(get_ack(1, vec![21, 12, 11, 1]));
+
+    let shortcut = get_ack(3, vec![21, 12, 11, 1]);
+    assert_packet_eq!(sc.probe(1).recv_timeout(timeout()).unwrap(), shortcut);
+    assert!(
+        sc.log().contains(&DroneEvent::ControllerShortcut(shortcut)),
+        "The ACK was not given to the SC, events: {:?}",
+        sc.log()
+    );
+}
+
+/// This function checks if an ACK whose next hop is in the middle of the route is given to the SC, which delivers it to
+/// the client with `hop_index` pointing at the client.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21), then D(11) and D(12) are unlinked
+pub fn generic_controller_shortcut_mid_route<T: Drone + Send + 'static>() {
+    let mut sc = SimController::new(chain_topology(0.0, 0.0).spawn::<T>());
+    sc.disconnect(11, 12);
+    thread::sleep(settle());
+
+    sc.probe(21).send(get_ack(1, vec![21, 12, 11, 1]));
+
+    assert_packet_eq!(
+        sc.probe(1).recv_timeout(timeout()).unwrap(),
+        get_ack(3, vec![21, 12, 11, 1])
+    );
+    Recorder::record_n(sc.events(), 1, timeout()).assert_exactly(&[
+        DroneEvent::ControllerShortcut(get_ack(2, vec![21, 12, 11, 1])),
+    ]);
+    assert_silent(sc.events(), quiet());
+}
+
+/// This function checks if a drone uses the links added and removed by the SC.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21), then D(11) <-> S(21) is added and removed
+pub fn generic_controller_connect<T: Drone + Send + 'static>() {
+    let mut sc = SimController::new(chain_topology(0.0, 0.0).spawn::<T>());
+    sc.connect(11, 21);
+    thread::sleep(settle());
+
+    let mut msg = create_sample_packet(1, vec![1, 11, 21]);
+    sc.probe(1).send(msg.clone());
+    msg.routing_header.hop_index = 2;
+    assert_packet_eq!(sc.probe(21).recv_timeout(timeout()).unwrap(), msg);
+
+    sc.disconnect(11, 21);
+    thread::sleep(settle());
+
+    sc.probe(1).send(create_sample_packet(1, vec![1, 11, 21]));
+    assert_packet_eq!(
+        sc.probe(1).recv_timeout(timeout()).unwrap(),
+        get_nack(1, vec![11, 1], NackType::ErrorInRouting(21))
+    );
+    assert_silent(sc.probe(21).receiver(), quiet());
+}
+
+/// This function checks if a drone crashed by the SC is avoided by its neighbours and exits.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21), then D(12) crashes
+pub fn generic_controller_crash<T: Drone + Send + 'static>() {
+    let mut sc = SimController::new(chain_topology(0.0, 0.0).spawn::<T>());
+    sc.crash(12);
+    sc.assert_exited(12, timeout());
+
+    sc.probe(1)
+        .send(create_sample_packet(1, vec![1, 11, 12, 21]));
+    assert_packet_eq!(
+        sc.probe(1).recv_timeout(timeout()).unwrap(),
+        get_nack(1, vec![11, 1], NackType::ErrorInRouting(12))
+    );
+}
+
+/// This function checks if a drone applies the PDR set by the SC, and if the SC logs the drop.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21)
+pub fn generic_controller_set_pdr<T: Drone + Send + 'static>() {
+    let sc = SimController::new(chain_topology(0.0, 0.0).spawn::<T>());
+    sc.set_pdr(11, 1.0);
+    thread::sleep(settle());
+
+    let msg = create_sample_packet(1, vec![1, 11, 12, 21]);
+    sc.probe(1).send(msg.clone());
+
+    let nack = get_nack(1, vec![11, 1], NackType::Dropped);
+    assert_packet_eq!(sc.probe(1).recv_timeout(timeout()).unwrap(), nack);
+    Recorder::record_n(sc.events(), 2, timeout())
+        .assert_exactly(&[DroneEvent::PacketDropped(msg), DroneEvent::PacketSent(nack)]);
+    assert_silent(sc.events(), quiet());
+    assert_silent(sc.probe(21).receiver(), quiet());
+    assert_eq!(
+        sc.log().len(),
+        2,
+        "Unexpected events in the log: {:?}",
+        sc.log()
+    );
+}
@@ -0,0 +1,78 @@
+use wg_internal::drone::Drone;
+use wg_internal::packet::{NackType, NodeType};
+
+use crate::differential::Scenario;
+use crate::flood_generics::create_sample_flood_req;
+use crate::fragment_generics::{create_sample_packet, get_ack, get_nack};
+
+/* THE FOLLOWING TESTS COMPARE YOUR DRONE WITH THE REFERENCE DRONE OF THIS CRATE */
+
+/// This function checks that a drone routes fragments, ACKs and NACKs as the reference drone, including the
+/// invalid routes.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12)
+/// D(11) <-> D(13)
+pub fn generic_differential_routing<T: Drone + Send + 'static>() {
+    Scenario::new(11, &[1, 12, 13])
+        .packet(create_sample_packet(1, vec![1, 11, 12, 21]))
+        .packet(create_sample_packet(1, vec![1, 11, 13]))
+        .packet(get_ack(1, vec![12, 11, 1]))
+        .packet(get_nack(1, vec![13, 11, 1], NackType::Dropped))
+        // Destination is drone
+        .packet(create_sample_packet(1, vec![1, 11]))
+        // Unexpected recipient
+        .packet(create_sample_packet(1, vec![1, 12, 21]))
+        // Error in routing
+        .packet(create_sample_packet(1, vec![1, 11, 14, 21]))
+        // ACK whose next hop is not a neighbour
+        .packet(get_ack(1, vec![21, 11, 14]))
+        .assert_matches_reference::<T>();
+}
+
+/// This function checks that a drone applies the SC commands as the reference drone, where the protocol leaves no
+/// choice.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12)
+/// D(11) <-> D(13)
+pub fn generic_differential_commands<T: Drone + Send + 'static>() {
+    Scenario::new(11, &[1, 12, 13])
+        .remove_sender(12)
+        .packet(create_sample_packet(1, vec![1, 11, 12, 21]))
+        .add_sender(14)
+        .packet(create_sample_packet(1, vec![1, 11, 14, 21]))
+        .set_packet_drop_rate(1.0)
+        .packet(create_sample_packet(1, vec![1, 11, 13, 21]))
+        .packet(get_ack(1, vec![13, 11, 1]))
+        .set_packet_drop_rate(0.0)
+        .packet(create_sample_packet(1, vec![1, 11, 13, 21]))
+        // No fragment after the crash: the protocol does not say which id goes in its `ErrorInRouting` NACK, see
+        // `generic_handle_crash`
+        .crash()
+        .packet(get_nack(1, vec![13, 11, 1], NackType::Dropped))
+        .packet(create_sample_flood_req(2, 1, vec![(1, NodeType::Client)]))
+        .assert_matches_reference::<T>();
+}
+
+/// This function checks that a drone takes part in floods as the reference drone.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12)
+/// D(11) <-> D(13)
+pub fn generic_differential_flood<T: Drone + Send + 'static>() {
+    Scenario::new(11, &[1, 12, 13])
+        .packet(create_sample_flood_req(1, 1, vec![(1, NodeType::Client)]))
+        // Known flood
+        .packet(create_sample_flood_req(
+            1,
+            1,
+            vec![(1, NodeType::Client), (12, NodeType::Drone)],
+        ))
+        // Same flood id, different initiator
+        .packet(create_sample_flood_req(
+            1,
+            2,
+            vec![(2, NodeType::Client), (13, NodeType::Drone)],
+        ))
+        // Initiator not in the path trace
+        .packet(create_sample_flood_req(3, 1, vec![]))
+        .assert_matches_reference::<T>();
+}
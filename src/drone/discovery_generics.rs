@@ -0,0 +1,42 @@
+use wg_internal::drone::Drone;
+
+use crate::discovery::{complete_topology, discover, grid_topology, ring_topology, Graph};
+use crate::topology::TopologyBuilder;
+
+/* THE FOLLOWING TESTS CHECKS IF A FLOOD OF YOUR DRONES DISCOVERS THE WHOLE NETWORK */
+
+/// Floods `topology` from C(1) and checks that the path traces of the responses describe exactly `topology`.
+fn discovers_topology<T: Drone + Send + 'static>(topology: TopologyBuilder) {
+    let expected = Graph::of_topology(&topology);
+    let net = topology.spawn::<T>();
+
+    discover(&net, 1, 1).assert_eq(&expected);
+}
+
+/// This function checks if the flood responses received by a client describe every drone and every link of a ring.
+/// ### Network Topology
+/// C(1) <-> D(11)
+/// D(11) <-> D(12) <-> D(13) <-> D(14) <-> D(15) <-> D(16) <-> D(11)
+pub fn generic_flood_discovery_ring<T: Drone + Send + 'static>() {
+    discovers_topology::<T>(ring_topology(6));
+}
+
+/// This function checks if the flood responses received by a client describe every drone and every link of a grid.
+/// ### Network Topology
+/// C(1) <-> D(11)
+/// D(11) <-> D(12) <-> D(13)
+/// D(14) <-> D(15) <-> D(16)
+/// D(17) <-> D(18) <-> D(19)
+/// every drone is also linked to the one below it
+pub fn generic_flood_discovery_grid<T: Drone + Send + 'static>() {
+    discovers_topology::<T>(grid_topology(3, 3));
+}
+
+/// This function checks if the flood responses received by a client describe every drone and every link of a mesh
+/// where every drone is linked to all the others.
+/// ### Network Topology
+/// C(1) <-> D(11)
+/// D(i) <-> D(j) for every i != j in 11..=15
+pub fn generic_flood_discovery_mesh<T: Drone + Send + 'static>() {
+    discovers_topology::<T>(complete_topology(5));
+}
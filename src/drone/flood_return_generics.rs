@@ -0,0 +1,105 @@
+use std::thread;
+use wg_internal::controller::{DroneCommand, DroneEvent};
+use wg_internal::drone::Drone;
+use wg_internal::network::{NodeId, SourceRoutingHeader};
+use wg_internal::packet::NodeType;
+
+use crate::assert_packet_eq;
+use crate::discovery::{drone_chain_topology, start_flood};
+use crate::flood_generics::{create_flood_res, create_sample_flood_req};
+use crate::recorder::{assert_silent, Recorder};
+use crate::timing::{quiet, settle, timeout};
+
+/* THE FOLLOWING TESTS CHECKS IF YOUR DRONE SENDS FLOOD RESPONSES BACK ACROSS SEVERAL HOPS */
+
+/// Path trace of a flood started by C(1) that went through `drones`.
+fn path_trace(drones: &[NodeId]) -> Vec<(NodeId, NodeType)> {
+    let mut trace = vec![(1, NodeType::Client)];
+    trace.extend(drones.iter().map(|id| (*id, NodeType::Drone)));
+    trace
+}
+
+/// This function checks if the flood response of the last drone of a chain reaches the client along the reversed
+/// path trace.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> D(13) <-> D(14) <-> D(15)
+pub fn generic_flood_res_multi_hop<T: Drone + Send + 'static>() {
+    let net = drone_chain_topology(5).spawn::<T>();
+    start_flood(&net, 1, 1);
+
+    let flood_res = create_flood_res(
+        1,
+        path_trace(&[11, 12, 13, 14, 15]),
+        SourceRoutingHeader::new(vec![15, 14, 13, 12, 11, 1], 5),
+    );
+    assert_packet_eq!(net.probe(1).recv_timeout(timeout()).unwrap(), flood_res);
+    assert_silent(net.probe(1).receiver(), quiet());
+}
+
+/// This function checks if a flood response crosses drones that crashed after the flood went through them: drones
+/// in crash mode still forward flood responses.
+/// The flood of C(1) reaches S(21), D(12) and D(14) crash before S(21) answers. The SC did not tell their neighbours yet.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> D(13) <-> D(14) <-> S(21)
+pub fn generic_flood_res_over_crashed<T: Drone + Send + 'static>() {
+    let net = drone_chain_topology(4).server(21).edge(14, 21).spawn::<T>();
+    start_flood(&net, 1, 1);
+
+    let trace = vec![
+        (1, NodeType::Client),
+        (11, NodeType::Drone),
+        (12, NodeType::Drone),
+        (13, NodeType::Drone),
+        (14, NodeType::Drone),
+    ];
+    assert_packet_eq!(
+        net.probe(21).recv_timeout(timeout()).unwrap(),
+        create_sample_flood_req(1, 1, trace.clone())
+    );
+
+    net.drone(12).command(DroneCommand::Crash);
+    net.drone(14).command(DroneCommand::Crash);
+    thread::sleep(settle());
+
+    let mut flood_res = create_flood_res(
+        1,
+        [trace, vec![(21, NodeType::Server)]].concat(),
+        SourceRoutingHeader::new(vec![21, 14, 13, 12, 11, 1], 1),
+    );
+    net.probe(21).send(flood_res.clone());
+
+    flood_res.routing_header.hop_index = 5;
+    assert_packet_eq!(net.probe(1).recv_timeout(timeout()).unwrap(), flood_res);
+    assert_silent(net.probe(1).receiver(), quiet());
+}
+
+/// This function checks if a flood response whose next hop was removed is given to the SC.
+/// D(15) receives the request directly and answers along the path trace, D(14) cannot reach D(13) anymore.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> D(13) <-> D(14) <-> D(15), D(13) removed
+pub fn generic_flood_res_removed_hop<T: Drone + Send + 'static>() {
+    let mut net = drone_chain_topology(5).spawn::<T>();
+    net.crash(13);
+    thread::sleep(settle());
+
+    net.drone(15)
+        .send(create_sample_flood_req(1, 1, path_trace(&[11, 12, 13, 14])));
+
+    let route = vec![15, 14, 13, 12, 11, 1];
+    let flood_res = create_flood_res(
+        1,
+        path_trace(&[11, 12, 13, 14, 15]),
+        SourceRoutingHeader::new(route.clone(), 1),
+    );
+    let shortcut = create_flood_res(
+        1,
+        path_trace(&[11, 12, 13, 14, 15]),
+        SourceRoutingHeader::new(route, 2),
+    );
+    Recorder::record_n(net.events(), 2, timeout()).assert_exactly(&[
+        DroneEvent::PacketSent(flood_res),
+        DroneEvent::ControllerShortcut(shortcut),
+    ]);
+    assert_silent(net.events(), quiet());
+    assert_silent(net.probe(1).receiver(), quiet());
+}
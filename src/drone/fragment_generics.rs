@@ -1,20 +1,18 @@
-use crossbeam::channel::unbounded;
-use std::collections::HashMap;
-use std::thread;
-use std::time::Duration;
 use wg_internal::controller::DroneEvent;
 use wg_internal::drone::Drone;
 use wg_internal::network::SourceRoutingHeader;
 use wg_internal::packet::{Fragment, Nack, NackType, Packet, PacketType};
 
-use crate::assert_matches_any;
+use crate::pdr_generics::pdr_topology;
+use crate::recorder::{assert_silent, Recorder};
+use crate::timing::{quiet, timeout};
+use crate::topology::{Network, TopologyBuilder};
+use crate::{assert_event_eq, assert_packet_eq};
 
 /* THE FOLLOWING TESTS CHECKS IF YOUR DRONE IS HANDLING CORRECTLY PACKETS (FRAGMENT) */
 
-const TIMEOUT: Duration = Duration::from_millis(400);
-
 /// Creates a sample packet for testing purposes. For convenience, using 1-10 for clients, 11-20 for drones and 21-30 for servers
-fn create_sample_packet(hop_index: usize, hops: Vec<u8>) -> Packet {
+pub(crate) fn create_sample_packet(hop_index: usize, hops: Vec<u8>) -> Packet {
     Packet::new_fragment(
         SourceRoutingHeader { hop_index, hops },
         1,
@@ -27,11 +25,11 @@ fn create_sample_packet(hop_index: usize, hops: Vec<u8>) -> Packet {
     )
 }
 
-fn get_ack(hop_index: usize, hops: Vec<u8>) -> Packet {
+pub(crate) fn get_ack(hop_index: usize, hops: Vec<u8>) -> Packet {
     Packet::new_ack(SourceRoutingHeader { hop_index, hops }, 1, 1)
 }
 
-fn get_nack(hop_index: usize, hops: Vec<u8>, nack_type: NackType) -> Packet {
+pub(crate) fn get_nack(hop_index: usize, hops: Vec<u8>, nack_type: NackType) -> Packet {
     Packet::new_nack(
         SourceRoutingHeader { hop_index, hops },
         1,
@@ -43,147 +41,82 @@ fn get_nack(hop_index: usize, hops: Vec<u8>, nack_type: NackType) -> Packet {
 }
 
 /// This function is used to test the packet forward functionality of a drone.
-/// The assert consists in checking if the "client" and "SC" receive the correct packet.
+/// The assert consists in checking if the "server" and "SC" receive the correct packet.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> S(21)
 pub fn generic_fragment_forward<T: Drone + Send + 'static>() {
-    // Drone 11
-    let (d_send, d_recv) = unbounded();
-    // Drone 12
-    let (d2_send, d2_recv) = unbounded::<Packet>();
-    // SC commands
-    let (_d_command_send, d_command_recv) = unbounded();
-    let (d_event_send, d_event_recv) = unbounded();
-
-    let mut drone = T::new(
-        11,
-        d_event_send,
-        d_command_recv,
-        d_recv.clone(),
-        HashMap::from([(12, d2_send.clone())]),
-        0.0,
-    );
-    // Spawn the drone's run method in a separate thread
-    thread::spawn(move || {
-        drone.run();
-    });
+    let net = pdr_topology(0.0).spawn::<T>();
 
-    let mut msg = create_sample_packet(1, vec![1, 11, 12, 21]);
+    let mut msg = create_sample_packet(1, vec![1, 11, 21]);
 
     // "Client" sends packet to d
-    d_send.send(msg.clone()).unwrap();
+    net.probe(1).send(msg.clone());
     msg.routing_header.hop_index = 2;
 
-    // d2 receives packet from d1
-    assert_eq!(d2_recv.recv_timeout(TIMEOUT).unwrap(), msg);
+    // "Server" receives packet from d
+    assert_packet_eq!(net.probe(21).recv_timeout(timeout()).unwrap(), msg);
     // SC listen for event from the drone
-    assert_eq!(
-        d_event_recv.recv_timeout(TIMEOUT).unwrap(),
+    assert_event_eq!(
+        net.events().recv_timeout(timeout()).unwrap(),
         DroneEvent::PacketSent(msg)
     );
 }
 
 /// Checks if the packet is dropped by one drone. The assert consists in checking if the "client" and "SC" receive the correct packet.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> S(21)
 pub fn generic_fragment_drop<T: Drone + Send + 'static>() {
-    // Client 1
-    let (c_send, c_recv) = unbounded();
-    // Drone 11
-    let (d_send, d_recv) = unbounded();
-    // Drone 12
-    let (d2_send, _d2_recv) = unbounded();
-    // SC commands
-    let (_d_command_send, d_command_recv) = unbounded();
-    let (d_event_send, d_event_recv) = unbounded();
-
-    let mut drone = T::new(
-        11,
-        d_event_send,
-        d_command_recv,
-        d_recv,
-        HashMap::from([(12, d2_send.clone()), (1, c_send.clone())]),
-        1.0,
-    );
-
-    // Spawn the drone's run method in a separate thread
-    thread::spawn(move || {
-        drone.run();
-    });
+    let net = pdr_topology(1.0).spawn::<T>();
 
-    let msg = create_sample_packet(1, vec![1, 11, 12, 21]);
+    let msg = create_sample_packet(1, vec![1, 11, 21]);
 
     // "Client" sends packet to the drone
-    d_send.send(msg.clone()).unwrap();
+    net.probe(1).send(msg.clone());
 
     let nack_packet = get_nack(1, vec![11, 1], NackType::Dropped);
 
     // Client listens for packet from the drone (Dropped Nack)
-    assert_eq!(c_recv.recv_timeout(TIMEOUT).unwrap(), nack_packet);
-
-    // SC must receive a PacketSent (Nack from the drone) and a PacketDropped
-    let sc_res = DroneEvent::PacketDropped(msg);
-    let sc_res2 = DroneEvent::PacketSent(nack_packet);
-    for _ in 0..2 {
-        let res = d_event_recv.recv_timeout(TIMEOUT);
-        if res.is_err() {
-            panic!(
-                "assertion `left == right` failed:\nleft: `{:?}`\nright1: `{:?}`\nright2: `{:?}`",
-                res, sc_res, sc_res2
-            );
-        }
-        let res = res.unwrap();
-        assert_matches_any!(res, sc_res, sc_res2);
-    }
+    assert_packet_eq!(net.probe(1).recv_timeout(timeout()).unwrap(), nack_packet);
+
+    // SC must receive a PacketSent (Nack from the drone) and a PacketDropped, nothing else
+    Recorder::record_n(net.events(), 2, timeout()).assert_exactly(&[
+        DroneEvent::PacketDropped(msg),
+        DroneEvent::PacketSent(nack_packet),
+    ]);
+    assert_silent(net.events(), quiet());
+    assert_silent(net.probe(21).receiver(), quiet());
+}
+
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21)
+pub(crate) fn chain_topology(pdr_11: f32, pdr_12: f32) -> TopologyBuilder {
+    TopologyBuilder::new()
+        .client(1)
+        .drone(11, pdr_11)
+        .drone(12, pdr_12)
+        .server(21)
+        .edge(1, 11)
+        .edge(11, 12)
+        .edge(12, 21)
 }
 
 /// Checks if the packet is dropped by the second drone. The first drone has 0% PDR and the second one 100% PDR, otherwise the test will fail sometimes.
 /// The assert is checking only the NACK received by the client (It does not care about the SC events).
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21)
 pub fn generic_chain_fragment_drop<T: Drone + Send + 'static>() {
-    // Client 1 channels
-    let (c_send, c_recv) = unbounded();
-    // Server 21 channels
-    let (s_send, _s_recv) = unbounded();
-    // Drone 11
-    let (d_send, d_recv) = unbounded();
-    // Drone 12
-    let (d12_send, d12_recv) = unbounded();
-    // SC - needed to not make the drone crash / send DroneEvents
-    let (_d_command_send, d_command_recv) = unbounded();
-    let (d_event_send, _d_event_recv) = unbounded();
-
-    // Drone 11
-    let mut drone = T::new(
-        11,
-        d_event_send.clone(),
-        d_command_recv.clone(),
-        d_recv,
-        HashMap::from([(12, d12_send.clone()), (1, c_send.clone())]),
-        0.0,
-    );
-    // Drone 12
-    let mut drone2 = T::new(
-        12,
-        d_event_send.clone(),
-        d_command_recv.clone(),
-        d12_recv,
-        HashMap::from([(11, d_send.clone()), (21, s_send.clone())]),
-        1.0,
-    );
-
-    // Spawn the drone's run method in a separate thread
-    thread::spawn(move || {
-        drone.run();
-    });
-
-    thread::spawn(move || {
-        drone2.run();
-    });
+    chain_fragment_drop(chain_topology(0.0, 1.0).spawn::<T>());
+}
 
+pub(crate) fn chain_fragment_drop(net: Network) {
     let msg = create_sample_packet(1, vec![1, 11, 12, 21]);
 
     // "Client" sends packet to the drone
-    d_send.send(msg.clone()).unwrap();
+    net.probe(1).send(msg);
 
     // Client receives an NACK originated from 'd2'
-    assert_eq!(
-        c_recv.recv_timeout(TIMEOUT).unwrap(),
+    assert_packet_eq!(
+        net.probe(1).recv_timeout(timeout()).unwrap(),
         Packet {
             pack_type: PacketType::Nack(Nack {
                 fragment_index: 1,
@@ -200,189 +133,168 @@ pub fn generic_chain_fragment_drop<T: Drone + Send + 'static>() {
 
 /// Checks if the packet can reach its destination. Both drones must have 0% PDR, otherwise the test will fail sometimes.
 /// The assert is checking only the ACK received by the client (It does not care about the SC events).
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21)
 pub fn generic_chain_fragment_ack<T: Drone + Send + 'static>() {
-    // Client 1
-    let (c_send, c_recv) = unbounded();
-    // Server 21
-    let (s_send, s_recv) = unbounded();
-    // Drone 11
-    let (d_send, d_recv) = unbounded();
-    // Drone 12
-    let (d12_send, d12_recv) = unbounded();
-    // SC - needed to not make the drone crash
-    let (_d_command_send, d_command_recv) = unbounded();
-    let (d_event_send, _d_event_recv) = unbounded();
-
-    // Drone 11
-    let mut drone = T::new(
-        11,
-        d_event_send.clone(),
-        d_command_recv.clone(),
-        d_recv,
-        HashMap::from([(12, d12_send.clone()), (1, c_send.clone())]),
-        0.0,
-    );
-    // Drone 12
-    let mut drone2 = T::new(
-        12,
-        d_event_send.clone(),
-        d_command_recv.clone(),
-        d12_recv,
-        HashMap::from([(11, d_send.clone()), (21, s_send.clone())]),
-        0.0,
-    );
-
-    // Spawn the drone's run method in a separate thread
-    thread::spawn(move || {
-        drone.run();
-    });
-
-    thread::spawn(move || {
-        drone2.run();
-    });
+    chain_fragment_ack(chain_topology(0.0, 0.0).spawn::<T>());
+}
 
+pub(crate) fn chain_fragment_ack(net: Network) {
     let mut msg = create_sample_packet(1, vec![1, 11, 12, 21]);
 
     // "Client" sends packet to the drone
-    d_send.send(msg.clone()).unwrap();
+    net.probe(1).send(msg.clone());
 
     msg.routing_header.hop_index = 3;
     // Server receives the fragment
-    assert_eq!(s_recv.recv_timeout(TIMEOUT).unwrap(), msg);
+    assert_packet_eq!(net.probe(21).recv_timeout(timeout()).unwrap(), msg);
 
     // Server sends an ACK
-    d12_send
-        .send(Packet::new_ack(
-            SourceRoutingHeader {
-                hop_index: 1,
-                hops: vec![21, 12, 11, 1],
-            },
-            1,
-            1,
-        ))
-        .unwrap();
+    net.probe(21).send(get_ack(1, vec![21, 12, 11, 1]));
 
     // Client receives an ACK originated from 's'
-    assert_eq!(
-        c_recv.recv_timeout(TIMEOUT).unwrap(),
+    assert_packet_eq!(
+        net.probe(1).recv_timeout(timeout()).unwrap(),
         get_ack(3, vec![21, 12, 11, 1])
     );
 }
 
 /// Checks if the packet containing an ACK is correctly forwarded by the drone.
-/// The assert consists in checking if the drone sends the packet to both the next drone and the SC.
+/// The assert consists in checking if the drone sends the packet to both the next node and the SC.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> S(21)
 pub fn generic_ack_forward<T: Drone + Send + 'static>() {
-    // Drone 11
-    let (d_send, d_recv) = unbounded();
-    // Drone 12
-    let (d12_send, d12_recv) = unbounded();
-    // SC - needed to not make the drone crash
-    let (_d_command_send, d_command_recv) = unbounded();
-    let (d_event_send, d_event_recv) = unbounded();
-
-    // Drone 11
-    let mut drone = T::new(
-        11,
-        d_event_send.clone(),
-        d_command_recv.clone(),
-        d_recv,
-        HashMap::from([(12, d12_send.clone())]),
-        0.0,
-    );
+    let net = pdr_topology(0.0).spawn::<T>();
 
-    // Spawn the drone's run method in a separate thread
-    thread::spawn(move || {
-        drone.run();
-    });
-
-    let mut ack = get_ack(1, vec![1, 11, 12, 21]);
+    let mut ack = get_ack(1, vec![1, 11, 21]);
 
     // "Client" sends packet to d11
-    d_send.send(ack.clone()).unwrap();
+    net.probe(1).send(ack.clone());
     ack.routing_header.hop_index = 2;
 
-    // d12 receives packet from d11
-    assert_eq!(d12_recv.recv_timeout(TIMEOUT).unwrap(), ack);
+    // "Server" receives packet from d11
+    assert_packet_eq!(net.probe(21).recv_timeout(timeout()).unwrap(), ack);
     // SC gets notified that the packet was sent by d11
-    assert_eq!(
-        d_event_recv.recv_timeout(TIMEOUT).unwrap(),
+    assert_event_eq!(
+        net.events().recv_timeout(timeout()).unwrap(),
         DroneEvent::PacketSent(ack)
     );
 }
 
 /// Checks if the packet containing an NACK is correctly forwarded by the drone.
-/// The assert consists in checking if the drone sends the packet to both the next drone and the SC.
+/// The assert consists in checking if the drone sends the packet to both the next node and the SC.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> S(21)
 pub fn generic_nack_forward<T: Drone + Send + 'static>() {
-    // Drone 11
-    let (d_send, d_recv) = unbounded();
-    // Drone 12
-    let (d12_send, d12_recv) = unbounded();
-    // SC - needed to not make the drone crash
-    let (_d_command_send, d_command_recv) = unbounded();
-    let (d_event_send, d_event_recv) = unbounded();
-
-    // Drone 11
-    let mut drone = T::new(
-        11,
-        d_event_send.clone(),
-        d_command_recv.clone(),
-        d_recv,
-        HashMap::from([(12, d12_send.clone())]),
-        0.0,
-    );
-
-    // Spawn the drone's run method in a separate thread
-    thread::spawn(move || {
-        drone.run();
-    });
+    let net = pdr_topology(0.0).spawn::<T>();
 
-    let mut nack = get_nack(1, vec![1, 11, 12, 21], NackType::Dropped);
+    let mut nack = get_nack(1, vec![1, 11, 21], NackType::Dropped);
 
     // "Client" sends packet to d11
-    d_send.send(nack.clone()).unwrap();
+    net.probe(1).send(nack.clone());
     nack.routing_header.hop_index = 2;
 
-    // d12 receives packet from d11
-    assert_eq!(d12_recv.recv_timeout(TIMEOUT).unwrap(), nack);
+    // "Server" receives packet from d11
+    assert_packet_eq!(net.probe(21).recv_timeout(timeout()).unwrap(), nack);
     // SC gets notified that the packet was sent by d11
-    assert_eq!(
-        d_event_recv.recv_timeout(TIMEOUT).unwrap(),
+    assert_event_eq!(
+        net.events().recv_timeout(timeout()).unwrap(),
         DroneEvent::PacketSent(nack)
     );
 }
 
+/// Checks if the drone answers with a `DestinationIsDrone` NACK when it is the last hop of the route.
+/// ### Network Topology
+/// C(1) <-> D(11)
 pub fn generic_destination_is_drone<T: Drone + Send + 'static>() {
-    // Client 1
-    let (c_send, c_recv) = unbounded();
-    // Drone 11
-    let (d_send, d_recv) = unbounded();
-    // SC - needed to not make the drone crash
-    let (_d_command_send, d_command_recv) = unbounded();
-    let (d_event_send, _d_event_recv) = unbounded();
-
-    // Drone 11
-    let mut drone = T::new(
-        11,
-        d_event_send.clone(),
-        d_command_recv.clone(),
-        d_recv,
-        HashMap::from([(1, c_send.clone())]),
-        0.0,
-    );
-
-    // Spawn the drone's run method in a separate thread
-    thread::spawn(move || {
-        drone.run();
-    });
+    let net = TopologyBuilder::new()
+        .client(1)
+        .drone(11, 0.0)
+        .edge(1, 11)
+        .spawn::<T>();
 
     let packet = create_sample_packet(1, vec![1, 11]);
 
     // "Client" sends packet to d11
-    d_send.send(packet.clone()).unwrap();
+    net.probe(1).send(packet);
 
     // "Client" expects a NACK with DestionationIsDrone
-    assert_eq!(
-        c_recv.recv_timeout(TIMEOUT).unwrap(),
+    assert_packet_eq!(
+        net.probe(1).recv_timeout(timeout()).unwrap(),
         get_nack(1, vec![11, 1], NackType::DestinationIsDrone)
     );
 }
+
+/// Checks if the second drone of the chain answers with an `ErrorInRouting` NACK when the next hop is not one of its neighbours.
+/// The NACK must follow the reversed route up to the client, and every hop must be notified to the SC.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21), D(13) does not exist
+pub fn generic_error_in_routing<T: Drone + Send + 'static>() {
+    let net = chain_topology(0.0, 0.0).spawn::<T>();
+
+    let mut msg = create_sample_packet(1, vec![1, 11, 12, 13, 21]);
+
+    // "Client" sends packet to d11
+    net.probe(1).send(msg.clone());
+
+    // "Client" receives the NACK originated from d12, forwarded by d11
+    let nack = get_nack(1, vec![12, 11, 1], NackType::ErrorInRouting(13));
+    let mut forwarded_nack = nack.clone();
+    forwarded_nack.routing_header.hop_index = 2;
+    assert_packet_eq!(
+        net.probe(1).recv_timeout(timeout()).unwrap(),
+        forwarded_nack
+    );
+
+    // SC is notified of the fragment sent by d11, the NACK sent by d12 and the NACK forwarded by d11
+    msg.routing_header.hop_index = 2;
+    Recorder::record_n(net.events(), 3, timeout()).assert_exactly(&[
+        DroneEvent::PacketSent(msg),
+        DroneEvent::PacketSent(nack),
+        DroneEvent::PacketSent(forwarded_nack),
+    ]);
+    assert_silent(net.events(), quiet());
+}
+
+/// Checks if the drone answers with an `UnexpectedRecipient` NACK, carrying its own id, when `hops[hop_index]` is not its id.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21)
+pub fn generic_unexpected_recipient<T: Drone + Send + 'static>() {
+    let net = chain_topology(0.0, 0.0).spawn::<T>();
+
+    // "Client" sends to d11 a packet meant for d12
+    net.probe(1)
+        .send_to(11, create_sample_packet(1, vec![1, 12, 21]));
+
+    let nack = get_nack(1, vec![11, 1], NackType::UnexpectedRecipient(11));
+    assert_packet_eq!(net.probe(1).recv_timeout(timeout()).unwrap(), nack);
+    Recorder::record_n(net.events(), 1, timeout()).assert_exactly(&[DroneEvent::PacketSent(nack)]);
+    assert_silent(net.events(), quiet());
+}
+
+/// Same as `generic_unexpected_recipient`, but the wrong packet reaches the second drone of the chain,
+/// so the NACK has to be routed back through d11.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21), D(13) does not exist
+pub fn generic_unexpected_recipient_chain<T: Drone + Send + 'static>() {
+    let net = chain_topology(0.0, 0.0).spawn::<T>();
+
+    // d12 receives a packet that d11 should have sent to d13
+    net.drone(12)
+        .send(create_sample_packet(2, vec![1, 11, 13, 21]));
+
+    // The NACK from d12 is forwarded by d11 to the client
+    let nack = get_nack(1, vec![12, 11, 1], NackType::UnexpectedRecipient(12));
+    let mut forwarded_nack = nack.clone();
+    forwarded_nack.routing_header.hop_index = 2;
+    assert_packet_eq!(
+        net.probe(1).recv_timeout(timeout()).unwrap(),
+        forwarded_nack
+    );
+
+    Recorder::record_n(net.events(), 2, timeout()).assert_exactly(&[
+        DroneEvent::PacketSent(nack),
+        DroneEvent::PacketSent(forwarded_nack),
+    ]);
+    assert_silent(net.events(), quiet());
+}
@@ -0,0 +1,23 @@
+use wg_internal::drone::Drone;
+
+use crate::fuzz::fuzz_packets;
+use crate::seed::test_seed;
+use crate::topology::boxed_drone;
+
+/* THE FOLLOWING TESTS SEND RANDOM PACKETS TO YOUR DRONE AND CHECK THE INVARIANTS OF THE PROTOCOL */
+
+/// Number of packets generated for every PDR.
+const CASES: usize = 200;
+
+/// This function sends arbitrary packets (valid or not) to a drone and checks that it never panics, only sends
+/// packets to their next hop with a `PacketSent` event, and always handles the packet somehow.
+/// A failing packet is shrunk to a minimal one, the seed generating it is reported.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12)
+/// D(11) <-> D(13)
+pub fn generic_fuzz_packets<T: Drone + Send + 'static>() {
+    let seed = test_seed();
+    for pdr in [0.0, 1.0] {
+        fuzz_packets(boxed_drone::<T>, pdr, seed, CASES);
+    }
+}
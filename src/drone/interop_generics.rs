@@ -0,0 +1,88 @@
+use std::panic::{self, AssertUnwindSafe};
+use wg_internal::network::NodeId;
+
+use crate::flood_generics::{known_flood_req, line_topology, new_flood_neighbours, star_topology};
+use crate::fragment_generics::{chain_fragment_ack, chain_fragment_drop, chain_topology};
+use crate::topology::{DroneFactory, DroneImpl};
+
+/* THE FOLLOWING TESTS CHECKS IF DIFFERENT DRONE IMPLEMENTATIONS WORK CORRECTLY TOGETHER */
+
+/// Runs `scenario` once for every ordered pair `(first, second)` of `impls`, including an implementation paired with itself.
+/// `scenario` receives a function telling which factory to use for a given drone id.
+/// All the pairs are run, the failing ones are reported together at the end.
+fn for_each_pair(impls: &[DroneImpl], scenario: impl Fn(&dyn Fn(NodeId) -> DroneFactory)) {
+    let mut failures = Vec::new();
+    for first in impls {
+        for second in impls {
+            let factory_for = |id: NodeId| {
+                if id == 11 {
+                    first.factory
+                } else {
+                    second.factory
+                }
+            };
+            let res = panic::catch_unwind(AssertUnwindSafe(|| scenario(&factory_for)));
+            if res.is_err() {
+                failures.push(format!("D(11) = {}, others = {}", first.name, second.name));
+            }
+        }
+    }
+    assert!(
+        failures.is_empty(),
+        "Scenario failed for the following implementations:\n{}",
+        failures.join("\n")
+    );
+}
+
+/// Same as `generic_chain_fragment_ack`, with D(11) and D(12) taken from every pair of `impls`.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21)
+pub fn interop_chain_fragment_ack(impls: &[DroneImpl]) {
+    for_each_pair(impls, |factory_for| {
+        chain_fragment_ack(chain_topology(0.0, 0.0).spawn_with(factory_for));
+    });
+}
+
+/// Same as `generic_chain_fragment_drop`, with D(11) and D(12) taken from every pair of `impls`.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21)
+pub fn interop_chain_fragment_drop(impls: &[DroneImpl]) {
+    for_each_pair(impls, |factory_for| {
+        chain_fragment_drop(chain_topology(0.0, 1.0).spawn_with(factory_for));
+    });
+}
+
+/// Same as `generic_new_flood_neighbours`, with D(11) taken from the first implementation of the pair and D(12), D(13) from the second one.
+/// ### Network Topology
+/// C(1) <-> D(11)
+/// D(11) <-> D(12)
+/// D(11) <-> D(13)
+pub fn interop_new_flood_neighbours(impls: &[DroneImpl]) {
+    for_each_pair(impls, |factory_for| {
+        new_flood_neighbours(star_topology().spawn_with(factory_for));
+    });
+}
+
+/// Same as `generic_known_flood_req`, with D(11) and D(12) taken from every pair of `impls`.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12)
+pub fn interop_known_flood_req(impls: &[DroneImpl]) {
+    for_each_pair(impls, |factory_for| {
+        known_flood_req(line_topology().spawn_with(factory_for));
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::reference::ReferenceDrone;
+
+    #[test]
+    fn reference_with_itself() {
+        let impls = [DroneImpl::of::<ReferenceDrone>()];
+        interop_chain_fragment_ack(&impls);
+        interop_chain_fragment_drop(&impls);
+        interop_new_flood_neighbours(&impls);
+        interop_known_flood_req(&impls);
+    }
+}
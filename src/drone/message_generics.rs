@@ -0,0 +1,136 @@
+use wg_internal::drone::Drone;
+use wg_internal::network::{NodeId, SourceRoutingHeader};
+use wg_internal::packet::{Packet, PacketType};
+
+use crate::fuzz::FuzzRng;
+use crate::message::{fragment_message, Reassembler};
+use crate::packet_diff::PacketSummary;
+use crate::recorder::{assert_silent, Recorder};
+use crate::seed::{test_seed, SeedGuard};
+use crate::timing::{quiet, timeout};
+use crate::topology::{Network, TopologyBuilder};
+
+/* THE FOLLOWING TESTS CHECKS IF A WHOLE MESSAGE CROSSES A NETWORK OF YOUR DRONES */
+
+/// Size of the message, not a multiple of the fragment size so that the last fragment is partial.
+const MESSAGE_LEN: usize = 5000;
+
+/// ### Network Topology
+/// C(1) <-> D(11)
+/// D(11) <-> D(12) <-> D(14)
+/// D(11) <-> D(13) <-> D(14)
+/// D(14) <-> S(21)
+pub(crate) fn diamond_topology() -> TopologyBuilder {
+    TopologyBuilder::new()
+        .client(1)
+        .drone(11, 0.0)
+        .drone(12, 0.0)
+        .drone(13, 0.0)
+        .drone(14, 0.0)
+        .server(21)
+        .edge(1, 11)
+        .edge(11, 12)
+        .edge(11, 13)
+        .edge(12, 14)
+        .edge(13, 14)
+        .edge(14, 21)
+}
+
+/// This function checks if a multi-kilobyte message split into many fragments reaches the server whole, and if the
+/// ACK of every fragment reaches the client exactly once.
+/// ### Network Topology
+/// C(1) <-> D(11)
+/// D(11) <-> D(12) <-> D(14)
+/// D(11) <-> D(13) <-> D(14)
+/// D(14) <-> S(21)
+pub fn generic_message_end_to_end<T: Drone + Send + 'static>() {
+    let seed = test_seed();
+    let _guard = SeedGuard::new(seed);
+    let mut rng = FuzzRng::new(seed);
+    let message: Vec<u8> = (0..MESSAGE_LEN).map(|_| rng.next_u64() as u8).collect();
+
+    let net = diamond_topology().spawn::<T>();
+    message_transfer(&net, vec![1, 11, 12, 14, 21], 7, &message);
+}
+
+/// Sends `message` from the client `hops[0]` to the server at the end of `hops`.
+/// The server acknowledges every fragment as soon as it arrives, along the reversed route.
+pub(crate) fn message_transfer(net: &Network, hops: Vec<NodeId>, session_id: u64, message: &[u8]) {
+    let client = net.probe(hops[0]);
+    let server = net.probe(*hops.last().unwrap());
+    let back: Vec<NodeId> = hops.iter().rev().copied().collect();
+
+    let fragments = fragment_message(session_id, message, hops.clone());
+    for fragment in &fragments {
+        client.send(fragment.clone());
+    }
+
+    let mut reassembler = Reassembler::new();
+    while !reassembler.is_complete() {
+        let packet = server.recv_timeout(timeout()).unwrap_or_else(|_| {
+            panic!(
+                "Fragments {:?} of {} never reached the server",
+                reassembler.missing(),
+                fragments.len()
+            )
+        });
+        assert_eq!(
+            packet.routing_header,
+            SourceRoutingHeader::new(hops.clone(), hops.len() - 1),
+            "Wrong routing header on a fragment received by the server"
+        );
+        reassembler.push(&packet);
+
+        let PacketType::MsgFragment(fragment) = &packet.pack_type else {
+            unreachable!("checked by the reassembler");
+        };
+        server.send(Packet::new_ack(
+            SourceRoutingHeader::new(back.clone(), 1),
+            session_id,
+            fragment.fragment_index,
+        ));
+    }
+    let received = reassembler.message().unwrap();
+    assert_eq!(
+        received.len(),
+        message.len(),
+        "The reassembled message has the wrong length"
+    );
+    if let Some(pos) = received.iter().zip(message).position(|(r, m)| r != m) {
+        panic!(
+            "The reassembled message differs from the one sent, first at byte {}: {} != {}",
+            pos, received[pos], message[pos]
+        );
+    }
+    assert_silent(server.receiver(), quiet());
+
+    // Every fragment is acknowledged exactly once
+    let acks = Recorder::record_n(client.receiver(), fragments.len(), timeout());
+    let mut acked = vec![0; fragments.len()];
+    for ack in acks.items() {
+        match &ack.pack_type {
+            PacketType::Ack(a) if ack.session_id == session_id => {
+                match acked.get_mut(a.fragment_index as usize) {
+                    Some(count) => *count += 1,
+                    None => panic!("ACK of the unknown fragment {}", a.fragment_index),
+                }
+            }
+            _ => panic!(
+                "Expected an ACK of session {}, got {}",
+                session_id,
+                PacketSummary(ack)
+            ),
+        }
+    }
+    let wrong: Vec<(usize, i32)> = acked
+        .into_iter()
+        .enumerate()
+        .filter(|(_, count)| *count != 1)
+        .collect();
+    assert!(
+        wrong.is_empty(),
+        "Fragments not acknowledged exactly once, as (fragment index, ACKs): {:?}",
+        wrong
+    );
+    assert_silent(client.receiver(), quiet());
+}
@@ -1,3 +1,19 @@
+pub mod controller_generics;
+pub mod differential_generics;
+pub mod discovery_generics;
 pub mod flood_generics;
+pub mod flood_return_generics;
 pub mod fragment_generics;
+pub mod fuzz_generics;
+pub mod interop_generics;
+pub mod message_generics;
+pub mod payload_generics;
+pub mod pdr_generics;
+pub mod reference;
+pub mod routing_generics;
 pub mod sc_generics;
+pub mod seeded;
+pub mod shortcut_generics;
+pub mod termination_generics;
+
+pub use seeded::SeededDrone;
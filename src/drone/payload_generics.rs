@@ -0,0 +1,142 @@
+use wg_internal::drone::Drone;
+use wg_internal::network::{NodeId, SourceRoutingHeader};
+use wg_internal::packet::{Fragment, Nack, NackType, Packet, PacketType};
+
+use crate::assert_packet_eq;
+use crate::fuzz::FuzzRng;
+use crate::recorder::assert_silent;
+use crate::seed::{test_seed, SeedGuard};
+use crate::timing::{quiet, timeout};
+use crate::topology::TopologyBuilder;
+
+/* THE FOLLOWING TESTS CHECKS IF YOUR DRONE FORWARDS PAYLOADS UNTOUCHED */
+
+/// Number of drones of the chain.
+const CHAIN_LEN: u8 = 6;
+/// Number of fragments sent through the chain by every test.
+const FRAGMENTS: usize = 24;
+
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> ... <-> D(10 + `drones`) <-> S(21)
+pub(crate) fn long_chain_topology(drones: u8, pdr_last: f32) -> TopologyBuilder {
+    let mut builder = TopologyBuilder::new().client(1).server(21);
+    let mut previous = 1;
+    for id in 11..11 + drones {
+        let pdr = if id == 10 + drones { pdr_last } else { 0.0 };
+        builder = builder.drone(id, pdr).edge(previous, id);
+        previous = id;
+    }
+    builder.edge(previous, 21)
+}
+
+/// Route from C(1) to S(21) through the whole chain.
+fn chain_route(drones: u8) -> Vec<NodeId> {
+    let mut hops = vec![1];
+    hops.extend(11..11 + drones);
+    hops.push(21);
+    hops
+}
+
+/// Fragment with a random payload, length and index, sent from C(1) along `hops`.
+/// The first lengths are the edge cases: empty, a single byte and a full fragment.
+fn random_fragment(rng: &mut FuzzRng, n: usize, hops: Vec<NodeId>) -> Packet {
+    let length = match n {
+        0 => 0,
+        1 => 1,
+        2 => 128,
+        _ => rng.below(129) as u8,
+    };
+    let mut data = [0; 128];
+    for byte in data.iter_mut() {
+        *byte = rng.next_u64() as u8;
+    }
+    let total_n_fragments = 1 + rng.below(1 << 20);
+    Packet::new_fragment(
+        SourceRoutingHeader::new(hops, 1),
+        rng.next_u64(),
+        Fragment {
+            fragment_index: rng.below(total_n_fragments),
+            total_n_fragments,
+            length,
+            data,
+        },
+    )
+}
+
+/// This function checks if random payloads of any valid length cross a chain of drones byte for byte, and if the ACK
+/// sent back by the server for every fragment reaches the client unchanged.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> ... <-> D(16) <-> S(21)
+pub fn generic_payload_integrity<T: Drone + Send + 'static>() {
+    let seed = test_seed();
+    let _guard = SeedGuard::new(seed);
+    let mut rng = FuzzRng::new(seed);
+    let net = long_chain_topology(CHAIN_LEN, 0.0).spawn::<T>();
+    let hops = chain_route(CHAIN_LEN);
+    let back: Vec<NodeId> = hops.iter().rev().copied().collect();
+
+    let fragments: Vec<Packet> = (0..FRAGMENTS)
+        .map(|n| random_fragment(&mut rng, n, hops.clone()))
+        .collect();
+    for fragment in &fragments {
+        net.probe(1).send(fragment.clone());
+    }
+
+    // Every drone handles the fragments in order, so they arrive in the order they were sent
+    for fragment in &fragments {
+        let mut expected = fragment.clone();
+        expected.routing_header.hop_index = hops.len() - 1;
+        assert_packet_eq!(net.probe(21).recv_timeout(timeout()).unwrap(), expected);
+    }
+    assert_silent(net.probe(21).receiver(), quiet());
+
+    for fragment in &fragments {
+        let ack = Packet::new_ack(
+            SourceRoutingHeader::new(back.clone(), 1),
+            fragment.session_id,
+            fragment_index(fragment),
+        );
+        net.probe(21).send(ack.clone());
+
+        let mut expected = ack;
+        expected.routing_header.hop_index = back.len() - 1;
+        assert_packet_eq!(net.probe(1).recv_timeout(timeout()).unwrap(), expected);
+    }
+}
+
+/// This function checks if the `Dropped` NACK sent by the last drone of a chain echoes the fragment index and the
+/// session of every dropped fragment.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> ... <-> D(16) <-> S(21)
+pub fn generic_payload_nack_index<T: Drone + Send + 'static>() {
+    let seed = test_seed();
+    let _guard = SeedGuard::new(seed);
+    let mut rng = FuzzRng::new(seed);
+    let net = long_chain_topology(CHAIN_LEN, 1.0).spawn::<T>();
+    let hops = chain_route(CHAIN_LEN);
+    // The NACK goes back from the last drone
+    let back: Vec<NodeId> = hops[..hops.len() - 1].iter().rev().copied().collect();
+
+    for n in 0..FRAGMENTS {
+        let fragment = random_fragment(&mut rng, n, hops.clone());
+        net.probe(1).send(fragment.clone());
+
+        let nack = Packet::new_nack(
+            SourceRoutingHeader::new(back.clone(), back.len() - 1),
+            fragment.session_id,
+            Nack {
+                fragment_index: fragment_index(&fragment),
+                nack_type: NackType::Dropped,
+            },
+        );
+        assert_packet_eq!(net.probe(1).recv_timeout(timeout()).unwrap(), nack);
+    }
+    assert_silent(net.probe(21).receiver(), quiet());
+}
+
+fn fragment_index(packet: &Packet) -> u64 {
+    match &packet.pack_type {
+        PacketType::MsgFragment(fragment) => fragment.fragment_index,
+        _ => unreachable!("not a fragment"),
+    }
+}
@@ -0,0 +1,128 @@
+use std::collections::HashSet;
+use wg_internal::controller::DroneEvent;
+use wg_internal::drone::Drone;
+use wg_internal::network::SourceRoutingHeader;
+use wg_internal::packet::{Fragment, Nack, NackType, Packet, PacketType};
+
+use crate::assert_matches_pattern;
+use crate::seed::{test_seed, SEED_ENV_VAR};
+use crate::stats::assert_binomial;
+use crate::timing::timeout;
+use crate::topology::{Network, TopologyBuilder};
+use crate::SeededDrone;
+
+/* THE FOLLOWING TESTS CHECKS IF YOUR DRONE DROPS PACKETS WITH THE RIGHT PROBABILITY */
+
+/// Number of fragments sent through the drone by the statistical tests.
+const TRIALS: u64 = 2000;
+
+/// Fragment number `index` of the statistical tests, sent from C(1) to S(21) through D(11).
+fn indexed_fragment(index: u64) -> Packet {
+    Packet::new_fragment(
+        SourceRoutingHeader::new(vec![1, 11, 21], 1),
+        index,
+        Fragment {
+            fragment_index: index,
+            total_n_fragments: TRIALS,
+            length: 128,
+            data: [1; 128],
+        },
+    )
+}
+
+/// ### Network Topology
+/// C(1) <-> D(11) <-> S(21)
+pub(crate) fn pdr_topology(pdr: f32) -> TopologyBuilder {
+    TopologyBuilder::new()
+        .client(1)
+        .drone(11, pdr)
+        .server(21)
+        .edge(1, 11)
+        .edge(11, 21)
+}
+
+/// Sends many fragments through a drone with 30% PDR and checks that the number of `Dropped` NACKs is compatible
+/// with a binomial distribution, so that the check does not fail randomly.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> S(21)
+pub fn generic_statistical_pdr<T: Drone + Send + 'static>() {
+    statistical_pdr(pdr_topology(0.3).spawn::<T>(), 0.3);
+}
+
+/// Same as [`generic_statistical_pdr`] with seeded drones, so that a failure can be replayed with the printed seed.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> S(21)
+pub fn generic_statistical_pdr_seeded<T: SeededDrone + Send + 'static>() {
+    statistical_pdr(pdr_topology(0.3).spawn_seeded::<T>(test_seed()), 0.3);
+}
+
+/// This function checks that two networks seeded alike drop exactly the same fragments.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> S(21)
+pub fn generic_seeded_pdr_replay<T: SeededDrone + Send + 'static>() {
+    let seed = test_seed();
+    let first = statistical_pdr(pdr_topology(0.3).spawn_seeded::<T>(seed), 0.3);
+    let replay = statistical_pdr(pdr_topology(0.3).spawn_seeded::<T>(seed), 0.3);
+    assert_eq!(
+        first, replay,
+        "The same seed dropped different fragments, replay with {}={}",
+        SEED_ENV_VAR, seed
+    );
+}
+
+/// Returns the session ids of the dropped fragments, sorted.
+pub(crate) fn statistical_pdr(net: Network, pdr: f32) -> Vec<u64> {
+    for index in 0..TRIALS {
+        net.probe(1).send(indexed_fragment(index));
+    }
+
+    // Dropped fragments come back to the client as NACKs
+    let mut dropped = Vec::new();
+    while let Ok(packet) = net.probe(1).recv_timeout(timeout()) {
+        assert_matches_pattern!(
+            packet.pack_type,
+            PacketType::Nack(Nack {
+                nack_type: NackType::Dropped,
+                ..
+            })
+        );
+        dropped.push(packet.session_id);
+    }
+    // The others reach the server
+    let mut forwarded = Vec::new();
+    while let Ok(packet) = net.probe(21).recv_timeout(timeout()) {
+        assert_matches_pattern!(packet.pack_type, PacketType::MsgFragment(_));
+        forwarded.push(packet.session_id);
+    }
+
+    // Every fragment is either dropped or forwarded, exactly once
+    let mut seen = HashSet::new();
+    for session_id in dropped.iter().chain(forwarded.iter()) {
+        assert!(
+            seen.insert(*session_id),
+            "Fragment {} was handled more than once",
+            session_id
+        );
+    }
+    assert_eq!(
+        seen.len() as u64,
+        TRIALS,
+        "Some fragments were neither dropped nor forwarded"
+    );
+
+    // Every drop is notified to the SC
+    let dropped_events = net
+        .events()
+        .try_iter()
+        .filter(|event| matches!(event, DroneEvent::PacketDropped(_)))
+        .count();
+    assert_eq!(
+        dropped_events,
+        dropped.len(),
+        "The number of PacketDropped events does not match the Dropped NACKs"
+    );
+
+    assert_binomial(dropped.len() as u64, TRIALS, pdr as f64);
+    dropped.sort_unstable();
+    dropped
+}
@@ -0,0 +1,311 @@
+use crossbeam::channel::{select_biased, Receiver, Sender};
+use std::collections::{HashMap, HashSet};
+use wg_internal::controller::{DroneCommand, DroneEvent};
+use wg_internal::drone::Drone;
+use wg_internal::network::{NodeId, SourceRoutingHeader};
+use wg_internal::packet::{
+    FloodRequest, FloodResponse, Nack, NackType, NodeType, Packet, PacketType,
+};
+
+use crate::SeededDrone;
+
+/// Straightforward implementation of the protocol, used as oracle by the differential tests.
+///
+/// The drone is deterministic for a PDR of `0.0` or `1.0`, other rates draw from a xorshift RNG that can be seeded
+/// through [`SeededDrone`].
+pub struct ReferenceDrone {
+    id: NodeId,
+    controller_send: Sender<DroneEvent>,
+    controller_recv: Receiver<DroneCommand>,
+    packet_recv: Receiver<Packet>,
+    packet_send: HashMap<NodeId, Sender<Packet>>,
+    pdr: f32,
+    /// `(flood_id, initiator_id)` of the flood requests already received.
+    known_floods: HashSet<(u64, NodeId)>,
+    crashing: bool,
+    rng: u64,
+}
+
+impl Drone for ReferenceDrone {
+    fn new(
+        id: NodeId,
+        controller_send: Sender<DroneEvent>,
+        controller_recv: Receiver<DroneCommand>,
+        packet_recv: Receiver<Packet>,
+        packet_send: HashMap<NodeId, Sender<Packet>>,
+        pdr: f32,
+    ) -> Self {
+        Self {
+            id,
+            controller_send,
+            controller_recv,
+            packet_recv,
+            packet_send,
+            pdr,
+            known_floods: HashSet::new(),
+            crashing: false,
+            rng: seed_rng(id as u64),
+        }
+    }
+
+    fn run(&mut self) {
+        let mut controller_open = true;
+        loop {
+            if self.crashing || !controller_open {
+                // Commands are ignored or there are none left, the remaining packets are handled until every sender
+                // is gone
+                match self.packet_recv.recv() {
+                    Ok(packet) => self.handle_packet(packet),
+                    Err(_) => return,
+                }
+                continue;
+            }
+            select_biased! {
+                recv(self.controller_recv) -> command => {
+                    match command {
+                        Ok(command) => self.handle_command(command),
+                        // A disconnected channel is always ready, it would starve the packets
+                        Err(_) => controller_open = false,
+                    }
+                },
+                recv(self.packet_recv) -> packet => {
+                    match packet {
+                        Ok(packet) => self.handle_packet(packet),
+                        Err(_) => return,
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl SeededDrone for ReferenceDrone {
+    fn set_seed(&mut self, seed: u64) {
+        self.rng = seed_rng(seed);
+    }
+}
+
+/// xorshift needs a non-zero state.
+fn seed_rng(seed: u64) -> u64 {
+    (seed ^ 0x9E37_79B9_7F4A_7C15) | 1
+}
+
+impl ReferenceDrone {
+    fn handle_command(&mut self, command: DroneCommand) {
+        match command {
+            DroneCommand::AddSender(id, sender) => {
+                self.packet_send.insert(id, sender);
+            }
+            DroneCommand::RemoveSender(id) => {
+                self.packet_send.remove(&id);
+            }
+            DroneCommand::SetPacketDropRate(pdr) => self.pdr = pdr,
+            DroneCommand::Crash => self.crashing = true,
+        }
+    }
+
+    fn handle_packet(&mut self, packet: Packet) {
+        if let PacketType::FloodRequest(request) = &packet.pack_type {
+            // Flood requests do not follow the routing header, and a crashing drone does not take part in floods
+            if !self.crashing {
+                self.handle_flood_request(request.clone(), &packet);
+            }
+            return;
+        }
+
+        let header = &packet.routing_header;
+        let is_fragment = matches!(packet.pack_type, PacketType::MsgFragment(_));
+        if header.hops.get(header.hop_index) != Some(&self.id) {
+            if is_fragment {
+                let pos = header.hop_index.min(header.hops.len());
+                self.send_nack(&packet, pos, NackType::UnexpectedRecipient(self.id));
+            } else {
+                self.shortcut(packet);
+            }
+            return;
+        }
+
+        let pos = header.hop_index;
+        if is_fragment && self.crashing {
+            self.send_nack(&packet, pos, NackType::ErrorInRouting(self.id));
+            return;
+        }
+        let Some(&next) = header.hops.get(pos + 1) else {
+            if is_fragment {
+                self.send_nack(&packet, pos, NackType::DestinationIsDrone);
+            } else {
+                self.shortcut(packet);
+            }
+            return;
+        };
+
+        if is_fragment {
+            if !self.packet_send.contains_key(&next) {
+                self.send_nack(&packet, pos, NackType::ErrorInRouting(next));
+                return;
+            }
+            if self.should_drop() {
+                self.event(DroneEvent::PacketDropped(packet.clone()));
+                self.send_nack(&packet, pos, NackType::Dropped);
+                return;
+            }
+        }
+
+        let mut packet = packet;
+        packet.routing_header.hop_index += 1;
+        self.forward(packet);
+    }
+
+    fn handle_flood_request(&mut self, mut request: FloodRequest, packet: &Packet) {
+        let previous = request
+            .path_trace
+            .last()
+            .map_or(request.initiator_id, |(id, _)| *id);
+        request.path_trace.push((self.id, NodeType::Drone));
+
+        let mut others: Vec<NodeId> = self
+            .packet_send
+            .keys()
+            .copied()
+            .filter(|n| *n != previous)
+            .collect();
+        others.sort_unstable();
+        let known = !self
+            .known_floods
+            .insert((request.flood_id, request.initiator_id));
+
+        if known || others.is_empty() {
+            // The flood ends here, the response goes back along the path trace
+            let mut hops: Vec<NodeId> =
+                request.path_trace.iter().rev().map(|(id, _)| *id).collect();
+            if hops.last() != Some(&request.initiator_id) {
+                hops.push(request.initiator_id);
+            }
+            let response = Packet::new_flood_response(
+                SourceRoutingHeader::new(hops, 1),
+                packet.session_id,
+                FloodResponse {
+                    flood_id: request.flood_id,
+                    path_trace: request.path_trace,
+                },
+            );
+            self.forward(response);
+            return;
+        }
+
+        for n in others {
+            let forwarded = Packet {
+                pack_type: PacketType::FloodRequest(request.clone()),
+                ..packet.clone()
+            };
+            self.send_to(n, forwarded);
+        }
+    }
+
+    /// Sends back a NACK for the packet received at position `pos` of its route.
+    fn send_nack(&self, packet: &Packet, pos: usize, nack_type: NackType) {
+        let fragment_index = match &packet.pack_type {
+            PacketType::MsgFragment(fragment) => fragment.fragment_index,
+            _ => 0,
+        };
+        let hops: Vec<NodeId> = std::iter::once(self.id)
+            .chain(packet.routing_header.hops[..pos].iter().rev().copied())
+            .collect();
+        let nack = Packet::new_nack(
+            SourceRoutingHeader::new(hops, 1),
+            packet.session_id,
+            Nack {
+                fragment_index,
+                nack_type,
+            },
+        );
+        self.forward(nack);
+    }
+
+    /// Sends `packet` to `hops[hop_index]`. ACKs, NACKs and flood responses that cannot be delivered go to the SC.
+    fn forward(&self, packet: Packet) {
+        let header = &packet.routing_header;
+        match header.hops.get(header.hop_index) {
+            Some(next) if self.packet_send.contains_key(next) => {
+                let next = *next;
+                self.send_to(next, packet);
+            }
+            _ => self.shortcut(packet),
+        }
+    }
+
+    fn send_to(&self, n: NodeId, packet: Packet) {
+        // A neighbour that is gone has crashed, the SC removes it shortly
+        let _ = self.packet_send[&n].send(packet.clone());
+        self.event(DroneEvent::PacketSent(packet));
+    }
+
+    fn shortcut(&self, packet: Packet) {
+        self.event(DroneEvent::ControllerShortcut(packet));
+    }
+
+    fn event(&self, event: DroneEvent) {
+        let _ = self.controller_send.send(event);
+    }
+
+    fn should_drop(&mut self) -> bool {
+        if self.pdr <= 0.0 {
+            return false;
+        }
+        if self.pdr >= 1.0 {
+            return true;
+        }
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        ((self.rng >> 40) as f32 / (1u64 << 24) as f32) < self.pdr
+    }
+}
+
+/// The oracle must pass the whole suite itself, so that its choices where the protocol leaves room are checked too.
+#[cfg(test)]
+mod conformance {
+    crate::conformance_suite!(super::ReferenceDrone, seeded);
+}
+
+#[cfg(test)]
+mod tests {
+    use crossbeam::channel::unbounded;
+    use std::collections::HashMap;
+    use std::thread;
+    use wg_internal::drone::Drone;
+
+    use super::ReferenceDrone;
+    use crate::assert_packet_eq;
+    use crate::fragment_generics::create_sample_packet;
+    use crate::timing::timeout;
+
+    #[test]
+    fn packets_are_handled_once_the_controller_is_gone() {
+        let (event_send, _event_recv) = unbounded();
+        let (command_send, command_recv) = unbounded();
+        let (packet_send, packet_recv) = unbounded();
+        let (d12_send, d12_recv) = unbounded();
+        let mut drone = ReferenceDrone::new(
+            11,
+            event_send,
+            command_recv,
+            packet_recv,
+            HashMap::from([(12, d12_send)]),
+            0.0,
+        );
+        let handle = thread::spawn(move || drone.run());
+
+        drop(command_send);
+        packet_send
+            .send(create_sample_packet(1, vec![1, 11, 12]))
+            .unwrap();
+        assert_packet_eq!(
+            d12_recv.recv_timeout(timeout()).unwrap(),
+            create_sample_packet(2, vec![1, 11, 12])
+        );
+
+        drop(packet_send);
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,134 @@
+use wg_internal::controller::DroneEvent;
+use wg_internal::drone::Drone;
+use wg_internal::network::NodeId;
+use wg_internal::packet::{Nack, NackType, Packet, PacketType};
+
+use crate::fragment_generics::{chain_topology, create_sample_packet, get_ack, get_nack};
+use crate::recorder::{assert_silent, Recorder};
+use crate::timing::{quiet, timeout};
+use crate::topology::Network;
+use crate::{assert_matches_pattern, assert_packet_eq};
+
+/* THE FOLLOWING TESTS CHECKS IF YOUR DRONE HANDLES MALFORMED ROUTING HEADERS WITHOUT PANICKING */
+
+/// Waits for the only reaction of the network to a fragment that D(11) cannot route: an `UnexpectedRecipient(11)` NACK.
+/// The route of the NACK is built from a malformed header, so it may be unusable: the NACK may be sent to a neighbour
+/// or given to the SC, but the fragment must not be forwarded.
+fn expect_unexpected_recipient(net: &Network) {
+    let events = Recorder::record_n(net.events(), 1, timeout());
+    assert_matches_pattern!(
+        events.items(),
+        [DroneEvent::PacketSent(Packet {
+            pack_type: PacketType::Nack(Nack {
+                nack_type: NackType::UnexpectedRecipient(11),
+                ..
+            }),
+            ..
+        }) | DroneEvent::ControllerShortcut(Packet {
+            pack_type: PacketType::Nack(Nack {
+                nack_type: NackType::UnexpectedRecipient(11),
+                ..
+            }),
+            ..
+        })]
+    );
+    assert_silent(net.events(), quiet());
+    assert_silent(net.probe(21).receiver(), quiet());
+}
+
+/// This function checks if a drone answers with an `UnexpectedRecipient` NACK a fragment whose `hop_index` is 0,
+/// i.e. pointing at the client that sent it.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21)
+pub fn generic_hop_index_zero<T: Drone + Send + 'static>() {
+    let net = chain_topology(0.0, 0.0).spawn::<T>();
+
+    net.probe(1)
+        .send_to(11, create_sample_packet(0, vec![1, 11, 12, 21]));
+
+    expect_unexpected_recipient(&net);
+}
+
+/// This function checks if a drone answers with an `UnexpectedRecipient` NACK a fragment whose `hop_index` is past
+/// the end of the route.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21)
+pub fn generic_hop_index_past_end<T: Drone + Send + 'static>() {
+    let net = chain_topology(0.0, 0.0).spawn::<T>();
+
+    net.probe(1)
+        .send_to(11, create_sample_packet(2, vec![1, 11]));
+    expect_unexpected_recipient(&net);
+
+    net.probe(1)
+        .send_to(11, create_sample_packet(7, vec![1, 11, 12, 21]));
+    expect_unexpected_recipient(&net);
+}
+
+/// This function checks if a drone handles packets with no route at all: fragments are answered with an
+/// `UnexpectedRecipient` NACK, ACKs cannot be dropped and go to the SC.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21)
+pub fn generic_empty_hops<T: Drone + Send + 'static>() {
+    let net = chain_topology(0.0, 0.0).spawn::<T>();
+
+    net.probe(1).send_to(11, create_sample_packet(0, vec![]));
+    expect_unexpected_recipient(&net);
+
+    let ack = get_ack(0, vec![]);
+    net.probe(1).send_to(11, ack.clone());
+    Recorder::record_n(net.events(), 1, timeout())
+        .assert_exactly(&[DroneEvent::ControllerShortcut(ack)]);
+    assert_silent(net.events(), quiet());
+}
+
+/// This function checks if a drone listed twice in a route relies on `hop_index` rather than on the position of its
+/// id: the fragment loops through D(11) and D(12) twice before reaching the server, the ACK does the same backwards.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21)
+pub fn generic_drone_twice_in_route<T: Drone + Send + 'static>() {
+    let net = chain_topology(0.0, 0.0).spawn::<T>();
+    let hops = vec![1, 11, 12, 11, 12, 21];
+
+    let mut msg = create_sample_packet(1, hops.clone());
+    net.probe(1).send(msg.clone());
+
+    msg.routing_header.hop_index = 5;
+    assert_packet_eq!(net.probe(21).recv_timeout(timeout()).unwrap(), msg);
+
+    let back: Vec<NodeId> = hops.into_iter().rev().collect();
+    net.probe(21).send(get_ack(1, back.clone()));
+    assert_packet_eq!(
+        net.probe(1).recv_timeout(timeout()).unwrap(),
+        get_ack(5, back)
+    );
+}
+
+/// This function checks if a drone routes a fragment whose previous hop is not the node that sent it.
+/// Drones do not know who sent a packet: the fragment is forwarded as usual, and a NACK goes back along the declared
+/// route. Its next hop, D(13), is not a neighbour, so the NACK is given to the SC.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21)
+pub fn generic_wrong_previous_hop<T: Drone + Send + 'static>() {
+    let hops = vec![1, 13, 11, 12, 21];
+
+    let net = chain_topology(0.0, 0.0).spawn::<T>();
+    let mut msg = create_sample_packet(2, hops.clone());
+    net.probe(1).send_to(11, msg.clone());
+    msg.routing_header.hop_index = 4;
+    assert_packet_eq!(net.probe(21).recv_timeout(timeout()).unwrap(), msg);
+    drop(net);
+
+    // D(11) drops every fragment
+    let net = chain_topology(1.0, 0.0).spawn::<T>();
+    let msg = create_sample_packet(2, hops);
+    net.probe(1).send_to(11, msg.clone());
+
+    let nack = get_nack(1, vec![11, 13, 1], NackType::Dropped);
+    let events = Recorder::record_n(net.events(), 2, timeout());
+    events.assert_exactly(&[
+        DroneEvent::PacketDropped(msg),
+        DroneEvent::ControllerShortcut(nack),
+    ]);
+    assert_silent(net.probe(1).receiver(), quiet());
+}
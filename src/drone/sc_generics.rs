@@ -1,67 +1,183 @@
 use crossbeam::channel::unbounded;
-use std::collections::HashMap;
 use std::thread;
-use std::time::Duration;
 use wg_internal::controller::DroneCommand;
 use wg_internal::drone::Drone;
-use wg_internal::packet::Packet;
+use wg_internal::network::SourceRoutingHeader;
+use wg_internal::packet::{Nack, NackType, NodeType, Packet, PacketType};
 
-const TIMEOUT: Duration = Duration::from_millis(400);
+use crate::flood_generics::create_sample_flood_req;
+use crate::fragment_generics::{chain_topology, create_sample_packet, get_ack, get_nack};
+use crate::recorder::assert_silent;
+use crate::timing::{quiet, settle, timeout};
+use crate::topology::TopologyBuilder;
+use crate::{assert_matches_pattern, assert_packet_eq};
 
+/// Checks if a drone reads the commands of the SC while it runs: once it received `Crash`, it no longer forwards
+/// fragments and answers them with an `ErrorInRouting` NACK. Its neighbours have not been told yet.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21)
 pub fn generic_receive_sc_command<T: Drone + Send + 'static>() {
-    // Drone 11
-    let (d_send, d_recv) = unbounded();
-    // Drone 12
-    let (d2_send, d2_recv) = unbounded::<Packet>();
-    // SC commands
-    let (d_command_send, d_command_recv) = unbounded();
-    let (d_event_send, d_event_recv) = unbounded();
-
-    let mut drone = T::new(
-        11,
-        d_event_send,
-        d_command_recv.clone(),
-        d_recv.clone(),
-        HashMap::from([(12, d2_send.clone())]),
-        0.0,
-    );
-    // Spawn the drone's run method in a separate thread
-    thread::spawn(move || {
-        drone.run();
-    });
-
-    d_command_send.send(DroneCommand::Crash).unwrap(); // in this case a Crash command is sent to the drone
-    assert_eq!(
-        d_command_recv.recv_timeout(TIMEOUT).unwrap(),
-        DroneCommand::Crash
+    let net = chain_topology(0.0, 0.0).spawn::<T>();
+
+    net.drone(11).command(DroneCommand::Crash);
+    thread::sleep(settle());
+
+    net.probe(1)
+        .send(create_sample_packet(1, vec![1, 11, 12, 21]));
+    assert_matches_pattern!(
+        net.probe(1).recv_timeout(timeout()).unwrap(),
+        Packet {
+            pack_type: PacketType::Nack(Nack {
+                fragment_index: 1,
+                nack_type: NackType::ErrorInRouting(_),
+            }),
+            routing_header,
+            ..
+        } if routing_header == SourceRoutingHeader::new(vec![11, 1], 1)
     );
+    assert_silent(net.probe(21).receiver(), quiet());
 }
 
+/// Checks the crash behaviour of a drone. The packets it receives after the `Crash` command must be handled as follows:
+/// - fragments: NACK with `ErrorInRouting`
+/// - ACK / NACK: forwarded
+/// - flood requests: dropped
+///
+/// After that the drone must exit, since its neighbours already removed it and no one else can send packets to it.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> S(21)
 pub fn generic_handle_crash<T: Drone + Send + 'static>() {
-    // Drone 11
-    let (d_send, d_recv) = unbounded();
+    let mut net = TopologyBuilder::new()
+        .client(1)
+        .drone(11, 0.0)
+        .server(21)
+        .edge(1, 11)
+        .edge(11, 21)
+        .spawn::<T>();
+    // Packets still on their way to d11 when it crashes
+    let d11_send = net.drone(11).packet_sender();
+
+    // Neighbours remove d11, then the SC crashes it
+    net.crash(11);
+    thread::sleep(settle());
+
+    d11_send
+        .send(create_sample_packet(1, vec![1, 11, 21]))
+        .unwrap();
+    d11_send.send(get_ack(1, vec![21, 11, 1])).unwrap();
+    d11_send
+        .send(get_nack(1, vec![21, 11, 1], NackType::Dropped))
+        .unwrap();
+    d11_send
+        .send(create_sample_flood_req(1, 1, vec![(1, NodeType::Client)]))
+        .unwrap();
+    drop(d11_send);
+
+    // The fragment is answered with an ErrorInRouting NACK
+    assert_matches_pattern!(
+        net.probe(1).recv_timeout(timeout()).unwrap(),
+        Packet {
+            pack_type: PacketType::Nack(Nack {
+                fragment_index: 1,
+                nack_type: NackType::ErrorInRouting(_),
+            }),
+            routing_header,
+            ..
+        } if routing_header == SourceRoutingHeader::new(vec![11, 1], 1)
+    );
+    // ACK and NACK are still forwarded
+    assert_packet_eq!(
+        net.probe(1).recv_timeout(timeout()).unwrap(),
+        get_ack(2, vec![21, 11, 1])
+    );
+    assert_packet_eq!(
+        net.probe(1).recv_timeout(timeout()).unwrap(),
+        get_nack(2, vec![21, 11, 1], NackType::Dropped)
+    );
+    // The flood request is dropped: nothing else reaches the client or the server
+    assert_silent(net.probe(1).receiver(), quiet());
+    assert_silent(net.probe(21).receiver(), quiet());
+
+    net.assert_exited(11, timeout());
+}
+
+/// Checks if a neighbour added with `AddSender` is used to forward packets.
+/// ### Network Topology
+/// C(1) <-> D(11), D(12) is added by the SC
+pub fn generic_add_sender<T: Drone + Send + 'static>() {
+    let net = TopologyBuilder::new()
+        .client(1)
+        .drone(11, 0.0)
+        .edge(1, 11)
+        .spawn::<T>();
     // Drone 12
-    let (d2_send, d2_recv) = unbounded::<Packet>();
-    // SC commands
-    let (d_command_send, d_command_recv) = unbounded();
-    let (d_event_send, d_event_recv) = unbounded();
-
-    let mut drone11 = T::new(
-        11,
-        d_event_send,
-        d_command_recv.clone(),
-        d_recv.clone(),
-        HashMap::from([(12, d2_send.clone())]),
-        0.0,
+    let (d12_send, d12_recv) = unbounded::<Packet>();
+
+    net.drone(11).command(DroneCommand::AddSender(12, d12_send));
+    thread::sleep(settle());
+
+    let mut msg = create_sample_packet(1, vec![1, 11, 12, 21]);
+    // "Client" sends packet to d11
+    net.probe(1).send(msg.clone());
+    msg.routing_header.hop_index = 2;
+
+    // d12 receives packet from d11
+    assert_packet_eq!(d12_recv.recv_timeout(timeout()).unwrap(), msg);
+}
+
+/// Checks if the drone stops using a neighbour removed with `RemoveSender` and answers with an `ErrorInRouting` NACK.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21), D(12) is removed from D(11) by the SC
+pub fn generic_remove_sender<T: Drone + Send + 'static>() {
+    let net = chain_topology(0.0, 0.0).spawn::<T>();
+
+    net.drone(11).command(DroneCommand::RemoveSender(12));
+    thread::sleep(settle());
+
+    // "Client" sends packet to d11
+    net.probe(1)
+        .send(create_sample_packet(1, vec![1, 11, 12, 21]));
+
+    // "Client" expects a NACK with ErrorInRouting, the packet never reaches the server
+    assert_packet_eq!(
+        net.probe(1).recv_timeout(timeout()).unwrap(),
+        get_nack(1, vec![11, 1], NackType::ErrorInRouting(12))
     );
-    // Spawn the drone's run method in a separate thread
-    thread::spawn(move || {
-        drone11.run();
-    });
-
-    d_command_send.send(DroneCommand::Crash).unwrap(); // in this case a Crash command is sent to the drone
-    assert_eq!(
-        d_command_recv.recv_timeout(TIMEOUT).unwrap(),
-        DroneCommand::Crash
+    assert_silent(net.probe(21).receiver(), quiet());
+}
+
+/// Checks if a PDR changed with `SetPacketDropRate` while the drone is running is applied to the following packets.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21)
+pub fn generic_set_packet_drop_rate<T: Drone + Send + 'static>() {
+    let net = chain_topology(0.0, 0.0).spawn::<T>();
+
+    let mut msg = create_sample_packet(1, vec![1, 11, 12, 21]);
+    // With 0% PDR the first packet reaches the server
+    net.probe(1).send(msg.clone());
+    msg.routing_header.hop_index = 3;
+    assert_packet_eq!(net.probe(21).recv_timeout(timeout()).unwrap(), msg);
+
+    net.drone(11).command(DroneCommand::SetPacketDropRate(1.0));
+    thread::sleep(settle());
+
+    // With 100% PDR the second one is dropped by d11
+    net.probe(1)
+        .send(create_sample_packet(1, vec![1, 11, 12, 21]));
+    assert_packet_eq!(
+        net.probe(1).recv_timeout(timeout()).unwrap(),
+        get_nack(1, vec![11, 1], NackType::Dropped)
     );
+    assert_silent(net.probe(21).receiver(), quiet());
+}
+
+/// Checks if a drone crashed by the SC exits once its neighbours have removed it.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21), D(12) is crashed by the SC
+pub fn generic_crash_exit<T: Drone + Send + 'static>() {
+    let mut net = chain_topology(0.0, 0.0).spawn::<T>();
+
+    net.crash(12);
+
+    net.assert_exited(12, timeout());
 }
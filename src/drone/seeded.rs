@@ -0,0 +1,20 @@
+use wg_internal::drone::Drone;
+
+/// Opt-in extension of [`Drone`] for implementations whose random choices (the packet drops) can be made
+/// reproducible.
+///
+/// The seeded tests call [`SeededDrone::set_seed`] on every drone after [`Drone::new`] and before [`Drone::run`].
+/// The seed is taken from the `RUSTEZE_SEED` environment variable, or chosen at random, and is printed when the
+/// test fails: running the test again with `RUSTEZE_SEED=<seed>` replays the same drops.
+///
+/// ```ignore
+/// impl SeededDrone for MyDrone {
+///     fn set_seed(&mut self, seed: u64) {
+///         self.rng = StdRng::seed_from_u64(seed);
+///     }
+/// }
+/// ```
+pub trait SeededDrone: Drone {
+    /// Reseeds the RNG deciding the drops. The same seed must lead to the same decisions for the same packets.
+    fn set_seed(&mut self, seed: u64);
+}
@@ -0,0 +1,67 @@
+use std::thread;
+use wg_internal::controller::{DroneCommand, DroneEvent};
+use wg_internal::drone::Drone;
+use wg_internal::network::SourceRoutingHeader;
+use wg_internal::packet::{NackType, NodeType, Packet};
+
+use crate::flood_generics::create_flood_res;
+use crate::fragment_generics::{chain_topology, get_ack, get_nack};
+use crate::recorder::{assert_silent, Recorder};
+use crate::timing::{quiet, settle, timeout};
+
+/* THE FOLLOWING TESTS CHECKS IF YOUR DRONE GIVES TO THE SC THE PACKETS IT CANNOT DELIVER */
+
+/// Sends `packet` to D(11) in two networks where its next hop is not a neighbour of D(11): first a node that never
+/// existed, then C(1) after D(11) removed it.
+/// `build` returns the packet for a route and a `hop_index`. The only reaction allowed is the `ControllerShortcut` of
+/// the packet, pointing at the missing hop: nothing is sent to any neighbour.
+fn expect_shortcut<T: Drone + Send + 'static>(build: impl Fn(Vec<u8>, usize) -> Packet) {
+    for (missing, removed) in [(5, false), (1, true)] {
+        let net = chain_topology(0.0, 0.0).spawn::<T>();
+        if removed {
+            net.drone(11).command(DroneCommand::RemoveSender(missing));
+            thread::sleep(settle());
+        }
+        let route = vec![21, 12, 11, missing];
+
+        net.drone(11).send(build(route.clone(), 2));
+
+        Recorder::record_n(net.events(), 1, timeout())
+            .assert_exactly(&[DroneEvent::ControllerShortcut(build(route, 3))]);
+        assert_silent(net.events(), quiet());
+        assert_silent(net.probe(1).receiver(), quiet());
+        assert_silent(net.probe(21).receiver(), quiet());
+    }
+}
+
+/// This function checks if an ACK whose next hop is not a neighbour is given to the SC.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21)
+pub fn generic_shortcut_ack<T: Drone + Send + 'static>() {
+    expect_shortcut::<T>(|hops, hop_index| get_ack(hop_index, hops));
+}
+
+/// This function checks if a NACK whose next hop is not a neighbour is given to the SC.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21)
+pub fn generic_shortcut_nack<T: Drone + Send + 'static>() {
+    expect_shortcut::<T>(|hops, hop_index| get_nack(hop_index, hops, NackType::Dropped));
+}
+
+/// This function checks if a flood response whose next hop is not a neighbour is given to the SC.
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> S(21)
+pub fn generic_shortcut_flood_res<T: Drone + Send + 'static>() {
+    expect_shortcut::<T>(|hops, hop_index| {
+        let path_trace = hops
+            .iter()
+            .rev()
+            .map(|id| match id {
+                21 => (*id, NodeType::Server),
+                11 | 12 => (*id, NodeType::Drone),
+                _ => (*id, NodeType::Client),
+            })
+            .collect();
+        create_flood_res(1, path_trace, SourceRoutingHeader::new(hops, hop_index))
+    });
+}
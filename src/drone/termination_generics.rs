@@ -0,0 +1,99 @@
+use std::collections::BTreeMap;
+use wg_internal::controller::DroneEvent;
+use wg_internal::drone::Drone;
+use wg_internal::network::NodeId;
+use wg_internal::packet::{NodeType, PacketType};
+
+use crate::discovery::{complete_topology, ring_topology, start_flood};
+use crate::recorder::Recorder;
+use crate::timing::{quiet, timeout};
+use crate::topology::TopologyBuilder;
+
+/* THE FOLLOWING TESTS CHECKS IF A FLOOD OF YOUR DRONES ENDS ON NETWORKS WITH CYCLES */
+
+type PathTrace = Vec<(NodeId, NodeType)>;
+
+/// Floods `topology` from C(1) twice, with two different flood ids, and checks the `FloodRequest`s sent by the drones:
+/// - the network is quiet again within a deadline
+/// - a drone forwards a flood at most once: all its requests of that flood have the same path trace, and there is at
+///   most one for every neighbour but the one it came from
+fn flood_terminates<T: Drone + Send + 'static>(topology: TopologyBuilder) {
+    let net = topology.spawn::<T>();
+    start_flood(&net, 1, 1);
+    start_flood(&net, 1, 2);
+
+    let deadline = timeout() * 5;
+    let events = Recorder::record_until_quiet(net.events(), quiet(), deadline);
+    if let Ok(event) = net.events().recv_timeout(quiet()) {
+        panic!(
+            "The network is still flooding after {:?}, {} events were sent, the last one: {:?}",
+            deadline,
+            events.items().len() + 1,
+            event
+        );
+    }
+
+    // Requests forwarded by every drone, per flood: the drone is the last node of the path trace
+    let mut forwarded: BTreeMap<(u64, NodeId), Vec<PathTrace>> = BTreeMap::new();
+    for event in events.items() {
+        let DroneEvent::PacketSent(packet) = event else {
+            continue;
+        };
+        if let PacketType::FloodRequest(request) = &packet.pack_type {
+            let Some((sender, _)) = request.path_trace.last() else {
+                panic!("Flood request sent with an empty path trace: {:?}", request);
+            };
+            forwarded
+                .entry((request.flood_id, *sender))
+                .or_default()
+                .push(request.path_trace.clone());
+        }
+    }
+    assert!(
+        !forwarded.is_empty(),
+        "No drone forwarded the flood requests"
+    );
+
+    for ((flood_id, drone), traces) in &forwarded {
+        assert!(
+            traces.iter().all(|trace| *trace == traces[0]),
+            "D({}) forwarded flood {} more than once, with the path traces {:?}",
+            drone,
+            flood_id,
+            traces
+        );
+        let bound = net.topology().neighbours(*drone).len().saturating_sub(1);
+        assert!(
+            traces.len() <= bound,
+            "D({}) sent {} requests of flood {}, but has only {} neighbours besides the one it came from",
+            drone,
+            traces.len(),
+            flood_id,
+            bound
+        );
+    }
+}
+
+/// This function checks if a flood ends on a triangle, each drone forwarding it at most once.
+/// ### Network Topology
+/// C(1) <-> D(11)
+/// D(11) <-> D(12) <-> D(13) <-> D(11)
+pub fn generic_flood_terminates_triangle<T: Drone + Send + 'static>() {
+    flood_terminates::<T>(ring_topology(3));
+}
+
+/// This function checks if a flood ends on a square, each drone forwarding it at most once.
+/// ### Network Topology
+/// C(1) <-> D(11)
+/// D(11) <-> D(12) <-> D(13) <-> D(14) <-> D(11)
+pub fn generic_flood_terminates_square<T: Drone + Send + 'static>() {
+    flood_terminates::<T>(ring_topology(4));
+}
+
+/// This function checks if a flood ends on a fully connected network, each drone forwarding it at most once.
+/// ### Network Topology
+/// C(1) <-> D(11)
+/// D(i) <-> D(j) for every i != j in 11..=16
+pub fn generic_flood_terminates_complete<T: Drone + Send + 'static>() {
+    flood_terminates::<T>(complete_topology(6));
+}
@@ -0,0 +1,72 @@
+use std::fs;
+use std::path::Path;
+use wg_internal::config::Config;
+
+use crate::topology::TopologyBuilder;
+use crate::validate::validate_config;
+
+impl TopologyBuilder {
+    /// Topology of a network-initialization file of `wg_internal::config`.
+    /// Every link becomes a single bidirectional edge, whether it is listed by one or by both of its endpoints.
+    ///
+    /// ```ignore
+    /// let net = TopologyBuilder::from_toml_file("config/double_chain.toml").spawn::<MyDrone>();
+    /// ```
+    pub fn from_config(config: &Config) -> Self {
+        let mut builder = Self::new();
+        for client in &config.client {
+            builder = builder.client(client.id);
+        }
+        for drone in &config.drone {
+            builder = builder.drone(drone.id, drone.pdr);
+        }
+        for server in &config.server {
+            builder = builder.server(server.id);
+        }
+
+        let links = config
+            .drone
+            .iter()
+            .map(|drone| (drone.id, &drone.connected_node_ids))
+            .chain(
+                config
+                    .client
+                    .iter()
+                    .map(|client| (client.id, &client.connected_drone_ids)),
+            )
+            .chain(
+                config
+                    .server
+                    .iter()
+                    .map(|server| (server.id, &server.connected_drone_ids)),
+            );
+        for (id, connected) in links {
+            for &n in connected {
+                if !builder.has_edge(id, n) {
+                    builder = builder.edge(id, n);
+                }
+            }
+        }
+        builder
+    }
+
+    /// Parses `toml` as a [`Config`], see [`TopologyBuilder::from_config`].
+    /// Panics with every violation if the file breaks a rule of the protocol.
+    pub fn from_toml_str(toml: &str) -> Self {
+        let config: Config = toml::from_str(toml)
+            .unwrap_or_else(|err| panic!("invalid network-initialization file: {}", err));
+        if let Err(err) = validate_config(&config) {
+            panic!("invalid network-initialization file: {}", err);
+        }
+        Self::from_config(&config)
+    }
+
+    /// Reads the network-initialization file at `path`, then parses and validates it as
+    /// [`TopologyBuilder::from_toml_str`] does.
+    pub fn from_toml_file(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let toml = fs::read_to_string(path)
+            .unwrap_or_else(|err| panic!("cannot read {}: {}", path.display(), err));
+        Self::from_toml_str(&toml)
+    }
+}
@@ -0,0 +1,129 @@
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use wg_internal::controller::DroneEvent;
+use wg_internal::network::NodeId;
+use wg_internal::packet::Packet;
+
+use crate::topology::{Network, Probe};
+
+/// Input channel of every node still reachable by the SC.
+type Inputs = Arc<Mutex<HashMap<NodeId, Sender<Packet>>>>;
+
+/// Stand-in for the simulation controller of a spawned [`Network`].
+/// A background thread reads every `DroneEvent`: it is logged, `ControllerShortcut` packets are delivered to the
+/// channel of their destination (the last hop), then the event is forwarded to [`SimController::events`].
+///
+/// ```ignore
+/// let mut sc = SimController::new(chain_topology(0.0, 0.0).spawn::<MyDrone>());
+/// sc.crash(12);
+/// sc.probe(1).send(create_sample_packet(1, vec![1, 11, 12, 21]));
+/// ```
+pub struct SimController {
+    net: Network,
+    inputs: Inputs,
+    log: Arc<Mutex<Vec<DroneEvent>>>,
+    event_recv: Receiver<DroneEvent>,
+}
+
+impl SimController {
+    /// Takes over the events of `net`: from now on they must be read from [`SimController::events`].
+    pub fn new(net: Network) -> Self {
+        let inputs: Inputs = Arc::new(Mutex::new(
+            net.topology()
+                .nodes()
+                .iter()
+                .filter_map(|(id, _)| net.input(*id).map(|send| (*id, send)))
+                .collect(),
+        ));
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let (event_send, event_recv) = unbounded();
+
+        let drone_events = net.events().clone();
+        let (thread_inputs, thread_log) = (inputs.clone(), log.clone());
+        // Ends once every drone has exited
+        thread::spawn(move || {
+            for event in drone_events.iter() {
+                // Logged first, so that the log already holds the shortcut once its packet is received
+                thread_log.lock().unwrap().push(event.clone());
+                if let DroneEvent::ControllerShortcut(packet) = &event {
+                    deliver(&thread_inputs, packet);
+                }
+                let _ = event_send.send(event);
+            }
+        });
+
+        Self {
+            net,
+            inputs,
+            log,
+            event_recv,
+        }
+    }
+
+    pub fn network(&self) -> &Network {
+        &self.net
+    }
+
+    pub fn probe(&self, id: NodeId) -> &Probe {
+        self.net.probe(id)
+    }
+
+    /// Events of every drone, once handled by the SC.
+    pub fn events(&self) -> &Receiver<DroneEvent> {
+        &self.event_recv
+    }
+
+    /// Every event handled so far, in the order they arrived.
+    pub fn log(&self) -> Vec<DroneEvent> {
+        self.log.lock().unwrap().clone()
+    }
+
+    /// Crashes drone `id`: its neighbours remove it, then it receives the `Crash` command.
+    /// Shortcuts are no longer delivered to it, so that its input channel can be closed.
+    pub fn crash(&mut self, id: NodeId) {
+        self.inputs.lock().unwrap().remove(&id);
+        self.net.crash(id);
+    }
+
+    pub fn set_pdr(&self, id: NodeId, pdr: f32) {
+        self.net.set_pdr(id, pdr);
+    }
+
+    /// Links `a` and `b`, both are given an `AddSender`.
+    pub fn connect(&mut self, a: NodeId, b: NodeId) {
+        self.net.connect(a, b);
+    }
+
+    /// Unlinks `a` and `b`, both are given a `RemoveSender`.
+    pub fn disconnect(&mut self, a: NodeId, b: NodeId) {
+        self.net.disconnect(a, b);
+    }
+
+    /// Panics if the thread of drone `id` is still running after `deadline` or if it panicked.
+    pub fn assert_exited(&mut self, id: NodeId, deadline: Duration) {
+        self.net.assert_exited(id, deadline);
+    }
+}
+
+impl Drop for SimController {
+    fn drop(&mut self) {
+        // Release the channels kept for shortcuts, the network then stops the drones
+        self.inputs.lock().unwrap().clear();
+    }
+}
+
+/// Sends `packet` to the node at the end of its route, with `hop_index` pointing at it as if the packet had travelled
+/// the whole route. Packets for unknown or crashed nodes are only logged.
+fn deliver(inputs: &Mutex<HashMap<NodeId, Sender<Packet>>>, packet: &Packet) {
+    let Some(&destination) = packet.routing_header.hops.last() else {
+        return;
+    };
+    if let Some(send) = inputs.lock().unwrap().get(&destination) {
+        let mut packet = packet.clone();
+        packet.routing_header.hop_index = packet.routing_header.hops.len() - 1;
+        let _ = send.send(packet);
+    }
+}
@@ -0,0 +1,283 @@
+use std::fmt::Debug;
+use wg_internal::controller::DroneEvent;
+use wg_internal::packet::Packet;
+
+use crate::packet_diff::{event_diff, packet_diff, EventSummary, PacketSummary};
+
+/// Unchanged lines kept around each difference, the others are elided.
+const CONTEXT_LINES: usize = 2;
+
+/// Line-by-line diff of the pretty `Debug` output of two values.
+/// Since every field is printed on its own line, the changed lines are the fields that differ.
+#[derive(Debug, Clone)]
+pub struct DebugDiff {
+    lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum DiffLine {
+    Same(String),
+    Actual(String),
+    Expected(String),
+}
+
+impl DebugDiff {
+    pub fn new(actual: &dyn Debug, expected: &dyn Debug) -> Self {
+        let actual = format!("{:#?}", actual);
+        let expected = format!("{:#?}", expected);
+        Self {
+            lines: diff_lines(
+                &actual.lines().collect::<Vec<_>>(),
+                &expected.lines().collect::<Vec<_>>(),
+            ),
+        }
+    }
+
+    /// Number of lines that differ, used to find the closest alternative.
+    pub fn distance(&self) -> usize {
+        self.lines
+            .iter()
+            .filter(|line| !matches!(line, DiffLine::Same(_)))
+            .count()
+    }
+
+    /// Renders the diff with `-` for the actual lines and `+` for the expected ones.
+    /// Runs of unchanged lines far from any difference are elided.
+    pub fn render(&self) -> String {
+        let near_change = |i: usize| {
+            let from = i.saturating_sub(CONTEXT_LINES);
+            let to = (i + CONTEXT_LINES + 1).min(self.lines.len());
+            self.lines[from..to]
+                .iter()
+                .any(|line| !matches!(line, DiffLine::Same(_)))
+        };
+
+        let mut out = String::new();
+        let mut elided = 0;
+        for (i, line) in self.lines.iter().enumerate() {
+            match line {
+                DiffLine::Same(_) if !near_change(i) => {
+                    elided += 1;
+                    continue;
+                }
+                _ if elided > 0 => {
+                    out.push_str(&format!("  ... {} identical lines\n", elided));
+                    elided = 0;
+                }
+                _ => {}
+            }
+            match line {
+                DiffLine::Same(l) => out.push_str(&format!("  {}\n", l)),
+                DiffLine::Actual(l) => out.push_str(&format!("- {}\n", l)),
+                DiffLine::Expected(l) => out.push_str(&format!("+ {}\n", l)),
+            }
+        }
+        if elided > 0 {
+            out.push_str(&format!("  ... {} identical lines\n", elided));
+        }
+        out
+    }
+}
+
+/// Longest-common-subsequence diff between two lists of lines.
+fn diff_lines(actual: &[&str], expected: &[&str]) -> Vec<DiffLine> {
+    let (n, m) = (actual.len(), expected.len());
+    // lcs[i][j] = length of the LCS of actual[i..] and expected[j..]
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if actual[i] == expected[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && actual[i] == expected[j] {
+            lines.push(DiffLine::Same(actual[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(DiffLine::Actual(actual[i].to_string()));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Expected(expected[j].to_string()));
+            j += 1;
+        }
+    }
+    lines
+}
+
+/// How `actual` compares with one alternative of `assert_matches_any!`.
+#[doc(hidden)]
+pub struct Comparison {
+    pub actual: String,
+    pub expected: String,
+    /// Number of differences, used to find the closest alternative.
+    pub distance: usize,
+    pub diff: String,
+}
+
+/// An actual value and one expected alternative, compared by [`FieldCompare`] or [`DebugCompare`].
+#[doc(hidden)]
+pub struct Pair<'a, A: ?Sized, E: ?Sized>(pub &'a A, pub &'a E);
+
+/// Packets and events are compared field by field, see [`packet_diff`].
+/// Called on `&Pair`, it takes precedence over [`DebugCompare`] (implemented for `&&Pair`) when both apply.
+#[doc(hidden)]
+pub trait FieldCompare {
+    fn compare(&self) -> Comparison;
+}
+
+impl FieldCompare for Pair<'_, Packet, Packet> {
+    fn compare(&self) -> Comparison {
+        let diffs = packet_diff(self.0, self.1);
+        Comparison {
+            actual: PacketSummary(self.0).to_string(),
+            expected: PacketSummary(self.1).to_string(),
+            distance: diffs.len(),
+            diff: format!("  {}\n", diffs.join("\n  ")),
+        }
+    }
+}
+
+impl FieldCompare for Pair<'_, DroneEvent, DroneEvent> {
+    fn compare(&self) -> Comparison {
+        let diffs = event_diff(self.0, self.1);
+        Comparison {
+            actual: EventSummary(self.0).to_string(),
+            expected: EventSummary(self.1).to_string(),
+            distance: diffs.len(),
+            diff: format!("  {}\n", diffs.join("\n  ")),
+        }
+    }
+}
+
+/// Any other type is compared with the line diff of its `Debug` output, see [`DebugDiff`].
+#[doc(hidden)]
+pub trait DebugCompare {
+    fn compare(&self) -> Comparison;
+}
+
+impl<A: Debug + ?Sized, E: Debug + ?Sized> DebugCompare for &Pair<'_, A, E> {
+    fn compare(&self) -> Comparison {
+        let diff = DebugDiff::new(&self.0, &self.1);
+        Comparison {
+            actual: format!("{:?}", self.0),
+            expected: format!("{:?}", self.1),
+            distance: diff.distance(),
+            diff: format!("(- actual, + expected)\n{}", diff.render()),
+        }
+    }
+}
+
+/// Checks `actual` against every alternative of `expected`.
+/// On mismatch returns a message listing every alternative, followed by the diff with the closest one.
+/// `compare` is given by `assert_matches_any!`, which picks the field diff for packets and events.
+#[doc(hidden)]
+pub fn match_any<A, E, F>(actual: &A, expected: &[&E], compare: F) -> Result<(), String>
+where
+    A: PartialEq<E> + Debug + ?Sized,
+    E: Debug + ?Sized,
+    F: Fn(&A, &E) -> Comparison,
+{
+    if expected.iter().any(|e| actual == *e) {
+        return Ok(());
+    }
+
+    let comparisons: Vec<Comparison> = expected.iter().map(|e| compare(actual, e)).collect();
+    let mut msg = String::from("Assertion failed: `res` does not match any expected values.\n");
+    if let Some(first) = comparisons.first() {
+        msg.push_str(&format!("Actual: `{}`\nExpected one of:\n", first.actual));
+    }
+    for (i, comparison) in comparisons.iter().enumerate() {
+        msg.push_str(&format!("[{}] `{}`\n", i, comparison.expected));
+    }
+
+    let closest = comparisons
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, comparison)| comparison.distance);
+    if let Some((i, comparison)) = closest {
+        msg.push_str(&format!("Closest is [{}]:\n{}", i, comparison.diff));
+    }
+    Err(msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use wg_internal::controller::DroneEvent;
+
+    use crate::assert_matches_any;
+    use crate::fragment_generics::create_sample_packet;
+
+    fn panic_message(f: impl FnOnce() + std::panic::UnwindSafe) -> String {
+        let payload = std::panic::catch_unwind(f).unwrap_err();
+        payload.downcast_ref::<String>().unwrap().clone()
+    }
+
+    #[test]
+    fn any_alternative_matches() {
+        assert_matches_any!(3, 1, 2, 3);
+        let packet = create_sample_packet(1, vec![1, 11, 12]);
+        assert_matches_any!(
+            packet,
+            create_sample_packet(2, vec![1, 11, 12]),
+            packet.clone()
+        );
+        let event = DroneEvent::PacketSent(packet.clone());
+        assert_matches_any!(
+            event,
+            DroneEvent::PacketDropped(packet.clone()),
+            event.clone()
+        );
+    }
+
+    #[test]
+    fn packets_are_diffed_by_field() {
+        let msg = panic_message(|| {
+            assert_matches_any!(
+                create_sample_packet(1, vec![1, 11, 12]),
+                create_sample_packet(3, vec![1, 13, 12, 21]),
+                create_sample_packet(2, vec![1, 11, 12])
+            )
+        });
+        assert!(
+            msg.contains("Closest is [1]:\n  hop_index: 1 != 2"),
+            "{}",
+            msg
+        );
+        assert!(!msg.contains("data: ["), "{}", msg);
+    }
+
+    #[test]
+    fn events_are_diffed_by_field() {
+        let packet = create_sample_packet(1, vec![1, 11, 12]);
+        let msg = panic_message(|| {
+            assert_matches_any!(
+                DroneEvent::PacketSent(packet.clone()),
+                DroneEvent::PacketDropped(packet.clone())
+            )
+        });
+        assert!(
+            msg.contains("Closest is [0]:\n  event: PacketSent != PacketDropped"),
+            "{}",
+            msg
+        );
+    }
+
+    #[test]
+    fn other_types_are_diffed_by_debug_line() {
+        let msg = panic_message(|| assert_matches_any!(vec![1, 2, 3], vec![1, 5, 3], vec![7, 8]));
+        assert!(
+            msg.contains("Closest is [0]:\n(- actual, + expected)\n"),
+            "{}",
+            msg
+        );
+        assert!(msg.contains("-     2,\n+     5,"), "{}", msg);
+    }
+}
@@ -0,0 +1,340 @@
+use crossbeam::channel::{unbounded, Receiver, Sender};
+use std::collections::{BTreeMap, HashMap};
+use std::thread::{self, JoinHandle};
+use std::time::Instant;
+use wg_internal::controller::{DroneCommand, DroneEvent};
+use wg_internal::drone::Drone;
+use wg_internal::network::NodeId;
+use wg_internal::packet::Packet;
+
+use crate::liveness::{join_until, JoinError};
+use crate::packet_diff::{packet_diff, EventSummary, PacketSummary};
+use crate::reference::ReferenceDrone;
+use crate::timing::{quiet, settle, timeout};
+use crate::topology::{boxed_drone, DroneFactory};
+
+/// One input of a [`Scenario`].
+/// Commands carrying a channel are described by the id of the neighbour, the channel is created by the runner.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    Packet(Packet),
+    AddSender(NodeId),
+    RemoveSender(NodeId),
+    SetPacketDropRate(f32),
+    Crash,
+}
+
+/// Inputs fed to a single drone, whose outputs are compared between the implementation under test and
+/// [`ReferenceDrone`].
+///
+/// ```ignore
+/// Scenario::new(11, &[1, 12])
+///     .packet(create_sample_packet(1, vec![1, 11, 12]))
+///     .remove_sender(12)
+///     .packet(create_sample_packet(1, vec![1, 11, 12]))
+///     .assert_matches_reference::<MyDrone>();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    id: NodeId,
+    neighbours: Vec<NodeId>,
+    pdr: f32,
+    steps: Vec<Step>,
+}
+
+/// Everything a drone emitted during a [`Scenario`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace {
+    /// Packets received by every neighbour, in the order they were sent.
+    pub sent: BTreeMap<NodeId, Vec<Packet>>,
+    pub events: Vec<DroneEvent>,
+    /// Message of the panic of the drone, if it panicked.
+    pub panic: Option<String>,
+}
+
+impl Scenario {
+    /// Drone `id` linked to `neighbours`, with a PDR of 0.
+    pub fn new(id: NodeId, neighbours: &[NodeId]) -> Self {
+        Self {
+            id,
+            neighbours: neighbours.to_vec(),
+            pdr: 0.0,
+            steps: Vec::new(),
+        }
+    }
+
+    pub fn pdr(mut self, pdr: f32) -> Self {
+        self.pdr = pdr;
+        self
+    }
+
+    pub fn step(mut self, step: Step) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    pub fn packet(self, packet: Packet) -> Self {
+        self.step(Step::Packet(packet))
+    }
+
+    pub fn add_sender(self, id: NodeId) -> Self {
+        self.step(Step::AddSender(id))
+    }
+
+    pub fn remove_sender(self, id: NodeId) -> Self {
+        self.step(Step::RemoveSender(id))
+    }
+
+    pub fn set_packet_drop_rate(self, pdr: f32) -> Self {
+        self.step(Step::SetPacketDropRate(pdr))
+    }
+
+    pub fn crash(self) -> Self {
+        self.step(Step::Crash)
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Runs the scenario on a drone built by `factory` and returns what it emitted.
+    /// Commands are given [`settle`] time before and after them, so that packets and commands are handled in the order
+    /// of the steps. Outputs are collected until the drone is [`quiet`], then it is crashed and must end within
+    /// [`timeout`].
+    pub fn record(&self, factory: DroneFactory) -> Trace {
+        Self::record_all(std::slice::from_ref(self), factory)
+            .pop()
+            .unwrap()
+    }
+
+    /// Same as [`Scenario::record`] for many scenarios at once, each on its own drone.
+    /// The drones run side by side, so that the outputs are collected in a single [`quiet`] window.
+    pub fn record_all(scenarios: &[Scenario], factory: DroneFactory) -> Vec<Trace> {
+        let mut runs: Vec<Run> = scenarios
+            .iter()
+            .map(|scenario| scenario.start(factory))
+            .collect();
+        loop {
+            thread::sleep(quiet());
+            let mut received = false;
+            for run in &mut runs {
+                received |= run.drain();
+            }
+            if !received {
+                break;
+            }
+        }
+        runs.into_iter().map(Run::finish).collect()
+    }
+
+    fn start(&self, factory: DroneFactory) -> Run {
+        let mut channels: BTreeMap<NodeId, (Sender<Packet>, Receiver<Packet>)> = BTreeMap::new();
+        for id in &self.neighbours {
+            channels.insert(*id, unbounded());
+        }
+        for step in &self.steps {
+            if let Step::AddSender(id) = step {
+                channels.entry(*id).or_insert_with(unbounded);
+            }
+        }
+
+        let (event_send, event_recv) = unbounded();
+        let (command_send, command_recv) = unbounded();
+        let (packet_send, packet_recv) = unbounded();
+        let neighbours: HashMap<NodeId, Sender<Packet>> = self
+            .neighbours
+            .iter()
+            .map(|id| (*id, channels[id].0.clone()))
+            .collect();
+        let mut drone = factory(
+            self.id,
+            event_send,
+            command_recv,
+            packet_recv,
+            neighbours,
+            self.pdr,
+        );
+        let handle = thread::spawn(move || drone.run());
+
+        let mut packets_queued = false;
+        for step in &self.steps {
+            let command = match step {
+                Step::Packet(packet) => {
+                    // The drone may have exited after a crash, its output tells what happened
+                    let _ = packet_send.send(packet.clone());
+                    packets_queued = true;
+                    continue;
+                }
+                Step::AddSender(id) => DroneCommand::AddSender(*id, channels[id].0.clone()),
+                Step::RemoveSender(id) => DroneCommand::RemoveSender(*id),
+                Step::SetPacketDropRate(pdr) => DroneCommand::SetPacketDropRate(*pdr),
+                Step::Crash => DroneCommand::Crash,
+            };
+            // Commands and packets travel on different channels: the packets queued before the command must be
+            // handled first, whichever channel the drone reads first
+            if packets_queued {
+                thread::sleep(settle());
+                packets_queued = false;
+            }
+            let _ = command_send.send(command);
+            thread::sleep(settle());
+        }
+
+        Run {
+            id: self.id,
+            sent: channels.keys().map(|id| (*id, Vec::new())).collect(),
+            events: Vec::new(),
+            channels,
+            event_recv,
+            command_send,
+            packet_send,
+            handle,
+        }
+    }
+    /// Runs the scenario on `T` and on [`ReferenceDrone`] and panics with every difference between their outputs.
+    /// Packets are compared per neighbour and in order, events in any order.
+    ///
+    /// Random drops cannot be compared: the PDR of the scenario must be `0.0` or `1.0`.
+    pub fn assert_matches_reference<T: Drone + Send + 'static>(&self) {
+        let deterministic = |pdr: f32| pdr == 0.0 || pdr == 1.0;
+        assert!(
+            deterministic(self.pdr)
+                && self.steps.iter().all(|step| match step {
+                    Step::SetPacketDropRate(pdr) => deterministic(*pdr),
+                    _ => true,
+                }),
+            "differential scenarios only support a PDR of 0 or 1"
+        );
+
+        let expected = self.record(boxed_drone::<ReferenceDrone>);
+        if let Some(panic) = &expected.panic {
+            panic!("The reference drone panicked: {}", panic);
+        }
+        let actual = self.record(boxed_drone::<T>);
+        let differences = trace_diff(&actual, &expected);
+        assert!(
+            differences.is_empty(),
+            "D({}) does not behave as the reference drone:\n  {}\nSteps:\n  {}",
+            self.id,
+            differences.join("\n  "),
+            self.steps
+                .iter()
+                .map(describe)
+                .collect::<Vec<_>>()
+                .join("\n  ")
+        );
+    }
+}
+
+fn describe(step: &Step) -> String {
+    match step {
+        Step::Packet(packet) => format!("Packet({})", PacketSummary(packet)),
+        other => format!("{:?}", other),
+    }
+}
+
+/// Returns one line per difference between `actual` and `expected`.
+pub fn trace_diff(actual: &Trace, expected: &Trace) -> Vec<String> {
+    let mut differences = Vec::new();
+    if let Some(panic) = &actual.panic {
+        differences.push(format!("panicked: {}", panic));
+    }
+
+    let no_packets = Vec::new();
+    let ids: Vec<&NodeId> = {
+        let mut ids: Vec<&NodeId> = actual.sent.keys().chain(expected.sent.keys()).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids
+    };
+    for id in ids {
+        let actual = actual.sent.get(id).unwrap_or(&no_packets);
+        let expected = expected.sent.get(id).unwrap_or(&no_packets);
+        for (i, (a, e)) in actual.iter().zip(expected).enumerate() {
+            let diff = packet_diff(a, e);
+            if !diff.is_empty() {
+                differences.push(format!("packet #{} sent to {}: {}", i, id, diff.join(", ")));
+            }
+        }
+        for (i, packet) in actual.iter().enumerate().skip(expected.len()) {
+            differences.push(format!(
+                "packet #{} sent to {} was not expected: {}",
+                i,
+                id,
+                PacketSummary(packet)
+            ));
+        }
+        for (i, packet) in expected.iter().enumerate().skip(actual.len()) {
+            differences.push(format!(
+                "packet #{} was not sent to {}: {}",
+                i,
+                id,
+                PacketSummary(packet)
+            ));
+        }
+    }
+
+    let mut unexpected: Vec<&DroneEvent> = actual.events.iter().collect();
+    for event in &expected.events {
+        match unexpected.iter().position(|e| *e == event) {
+            Some(pos) => {
+                unexpected.remove(pos);
+            }
+            None => differences.push(format!("missing event: {}", EventSummary(event))),
+        }
+    }
+    for event in unexpected {
+        differences.push(format!("unexpected event: {}", EventSummary(event)));
+    }
+    differences
+}
+
+/// A drone running a [`Scenario`], with what it emitted so far.
+struct Run {
+    id: NodeId,
+    channels: BTreeMap<NodeId, (Sender<Packet>, Receiver<Packet>)>,
+    event_recv: Receiver<DroneEvent>,
+    command_send: Sender<DroneCommand>,
+    packet_send: Sender<Packet>,
+    handle: JoinHandle<()>,
+    sent: BTreeMap<NodeId, Vec<Packet>>,
+    events: Vec<DroneEvent>,
+}
+
+impl Run {
+    /// Collects what arrived since the last call, returns whether anything did.
+    fn drain(&mut self) -> bool {
+        let mut received = false;
+        for (id, (_, recv)) in &self.channels {
+            let packets = self.sent.get_mut(id).unwrap();
+            let before = packets.len();
+            packets.extend(recv.try_iter());
+            received |= packets.len() > before;
+        }
+        let before = self.events.len();
+        self.events.extend(self.event_recv.try_iter());
+        received | (self.events.len() > before)
+    }
+
+    fn finish(self) -> Trace {
+        // The drone exits once it crashed and its packet channel is closed
+        let _ = self.command_send.send(DroneCommand::Crash);
+        drop(self.packet_send);
+        let panic = match join_until(self.handle, Instant::now() + timeout()) {
+            Ok(()) => None,
+            Err(JoinError::Panicked(msg)) => Some(msg),
+            Err(JoinError::StillRunning) => panic!(
+                "D({}): still running {:?} after the scenario ended",
+                self.id,
+                timeout()
+            ),
+        };
+        let mut sent = self.sent;
+        sent.retain(|_, packets| !packets.is_empty());
+        Trace {
+            sent,
+            events: self.events,
+            panic,
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use wg_internal::network::{NodeId, SourceRoutingHeader};
+use wg_internal::packet::{FloodRequest, NodeType, Packet, PacketType};
+
+use crate::packet_diff::PacketSummary;
+use crate::recorder::Recorder;
+use crate::timing::{quiet, timeout};
+use crate::topology::{Network, NodeRole, TopologyBuilder};
+
+/// ### Network Topology
+/// C(1) <-> D(11) <-> D(12) <-> ... <-> D(10 + `drones`)
+pub fn drone_chain_topology(drones: u8) -> TopologyBuilder {
+    let mut builder = TopologyBuilder::new().client(1);
+    let mut previous = 1;
+    for id in 11..11 + drones {
+        builder = builder.drone(id, 0.0).edge(previous, id);
+        previous = id;
+    }
+    builder
+}
+
+/// ### Network Topology
+/// C(1) <-> D(11)
+/// D(11) <-> D(12) <-> ... <-> D(10 + `drones`) <-> D(11)
+pub fn ring_topology(drones: u8) -> TopologyBuilder {
+    let mut builder = TopologyBuilder::new().client(1).edge(1, 11);
+    for i in 0..drones {
+        builder = builder
+            .drone(11 + i, 0.0)
+            .edge(11 + i, 11 + (i + 1) % drones);
+    }
+    builder
+}
+
+/// `width` x `height` grid of drones, numbered row by row from D(11).
+/// ### Network Topology
+/// C(1) <-> D(11)
+/// D(11 + `width` * row + col) <-> the drones on its right and below it
+pub fn grid_topology(width: u8, height: u8) -> TopologyBuilder {
+    let mut builder = TopologyBuilder::new().client(1).edge(1, 11);
+    for row in 0..height {
+        for col in 0..width {
+            let id = 11 + row * width + col;
+            builder = builder.drone(id, 0.0);
+            if col + 1 < width {
+                builder = builder.edge(id, id + 1);
+            }
+            if row + 1 < height {
+                builder = builder.edge(id, id + width);
+            }
+        }
+    }
+    builder
+}
+
+/// Fully connected mesh of drones.
+/// ### Network Topology
+/// C(1) <-> D(11)
+/// D(i) <-> D(j) for every i != j in 11..=10 + `drones`
+pub fn complete_topology(drones: u8) -> TopologyBuilder {
+    let mut builder = TopologyBuilder::new().client(1).edge(1, 11);
+    for i in 11..11 + drones {
+        builder = builder.drone(i, 0.0);
+        for j in i + 1..11 + drones {
+            builder = builder.edge(i, j);
+        }
+    }
+    builder
+}
+
+/// Nodes and links of a network, either declared by a topology or discovered by a flood.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Graph {
+    pub nodes: BTreeMap<NodeId, NodeType>,
+    /// Links stored once, as `(lower id, higher id)`.
+    pub edges: BTreeSet<(NodeId, NodeId)>,
+}
+
+impl Graph {
+    /// The graph declared by `topology`.
+    pub fn of_topology(topology: &TopologyBuilder) -> Self {
+        let mut graph = Self::default();
+        for (id, role) in topology.nodes() {
+            let node_type = match role {
+                NodeRole::Client => NodeType::Client,
+                NodeRole::Drone { .. } => NodeType::Drone,
+                NodeRole::Server => NodeType::Server,
+            };
+            graph.nodes.insert(*id, node_type);
+        }
+        for (a, b) in topology.edges() {
+            graph.add_edge(*a, *b);
+        }
+        graph
+    }
+
+    /// Adds the nodes of `path_trace` and a link between every two consecutive nodes.
+    pub fn add_path_trace(&mut self, path_trace: &[(NodeId, NodeType)]) {
+        for (id, node_type) in path_trace {
+            self.nodes.insert(*id, *node_type);
+        }
+        for pair in path_trace.windows(2) {
+            self.add_edge(pair[0].0, pair[1].0);
+        }
+    }
+
+    pub fn add_edge(&mut self, a: NodeId, b: NodeId) {
+        self.edges.insert((a.min(b), a.max(b)));
+    }
+
+    /// Panics, listing the differences, if `self` is not `expected`.
+    pub fn assert_eq(&self, expected: &Graph) {
+        assert!(
+            self == expected,
+            "The graphs differ.\n{}",
+            GraphDiff(self, expected)
+        );
+    }
+}
+
+/// Differences between a graph (left) and the expected one (right), one per line.
+struct GraphDiff<'a>(&'a Graph, &'a Graph);
+
+impl fmt::Display for GraphDiff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (graph, expected) = (self.0, self.1);
+        for (id, node_type) in &expected.nodes {
+            match graph.nodes.get(id) {
+                None => writeln!(f, "missing node {}", id)?,
+                Some(found) if found != node_type => writeln!(
+                    f,
+                    "node {} is a {:?}, expected a {:?}",
+                    id, found, node_type
+                )?,
+                Some(_) => {}
+            }
+        }
+        for id in graph.nodes.keys() {
+            if !expected.nodes.contains_key(id) {
+                writeln!(f, "unexpected node {}", id)?;
+            }
+        }
+        for (a, b) in expected.edges.difference(&graph.edges) {
+            writeln!(f, "missing link {} <-> {}", a, b)?;
+        }
+        for (a, b) in graph.edges.difference(&expected.edges) {
+            writeln!(f, "unexpected link {} <-> {}", a, b)?;
+        }
+        Ok(())
+    }
+}
+
+/// Starts a flood from the probe `initiator`: a `FloodRequest` is sent to each of its neighbours.
+pub fn start_flood(net: &Network, initiator: NodeId, flood_id: u64) {
+    let node_type = match net.topology().role(initiator) {
+        Some(NodeRole::Server) => NodeType::Server,
+        _ => NodeType::Client,
+    };
+    let request = Packet {
+        pack_type: PacketType::FloodRequest(FloodRequest {
+            flood_id,
+            initiator_id: initiator,
+            path_trace: vec![(initiator, node_type)],
+        }),
+        routing_header: SourceRoutingHeader {
+            hop_index: 0,
+            hops: Vec::new(),
+        },
+        session_id: flood_id,
+    };
+    for n in net.topology().neighbours(initiator) {
+        net.probe(initiator).send_to(n, request.clone());
+    }
+}
+
+/// Floods the network from the probe `initiator` and builds the graph described by the responses it receives.
+/// Responses are collected until the initiator is [`quiet`], every one must be routed back to the initiator along the
+/// reversed path trace.
+pub fn discover(net: &Network, initiator: NodeId, flood_id: u64) -> Graph {
+    start_flood(net, initiator, flood_id);
+
+    let responses =
+        Recorder::record_until_quiet(net.probe(initiator).receiver(), quiet(), timeout() * 10);
+    assert!(
+        !responses.items().is_empty(),
+        "No flood response reached {}",
+        initiator
+    );
+
+    let mut graph = Graph::default();
+    for packet in responses.items() {
+        let PacketType::FloodResponse(response) = &packet.pack_type else {
+            panic!("Expected a flood response, got {}", PacketSummary(packet));
+        };
+        assert_eq!(
+            response.flood_id,
+            flood_id,
+            "Wrong flood id in {}",
+            PacketSummary(packet)
+        );
+        let back: Vec<NodeId> = response
+            .path_trace
+            .iter()
+            .rev()
+            .map(|(id, _)| *id)
+            .collect();
+        assert!(
+            packet.routing_header.hops == back && packet.routing_header.hop_index + 1 == back.len(),
+            "The route of {} is not the reversed path trace",
+            PacketSummary(packet)
+        );
+        graph.add_path_trace(&response.path_trace);
+    }
+    graph
+}
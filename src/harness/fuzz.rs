@@ -0,0 +1,384 @@
+use std::collections::HashSet;
+use wg_internal::controller::DroneEvent;
+use wg_internal::network::{NodeId, SourceRoutingHeader};
+use wg_internal::packet::{
+    Ack, FloodRequest, FloodResponse, Fragment, Nack, NackType, NodeType, Packet, PacketType,
+};
+
+use crate::differential::{Scenario, Trace};
+use crate::packet_diff::{EventSummary, PacketSummary};
+use crate::seed::SEED_ENV_VAR;
+use crate::topology::DroneFactory;
+
+/// Id of the drone receiving the generated packets.
+pub const FUZZ_DRONE: NodeId = 11;
+/// Neighbours of [`FUZZ_DRONE`]: a client and two drones.
+pub const FUZZ_NEIGHBOURS: [NodeId; 3] = [1, 12, 13];
+/// Ids used in the generated routes and path traces: the drone, its neighbours and nodes it does not know.
+const IDS: [NodeId; 6] = [1, 11, 12, 13, 14, 21];
+
+/// Small xorshift generator, so that a seed always produces the same packets.
+#[derive(Debug, Clone)]
+pub struct FuzzRng(u64);
+
+impl FuzzRng {
+    pub fn new(seed: u64) -> Self {
+        Self((seed ^ 0x2545_F491_4F6C_DD1D) | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Uniform in `0..n`.
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+
+    pub fn chance(&mut self, one_in: u64) -> bool {
+        self.below(one_in) == 0
+    }
+
+    fn id(&mut self) -> NodeId {
+        IDS[self.below(IDS.len() as u64) as usize]
+    }
+
+    fn node_type(&mut self) -> NodeType {
+        match self.below(3) {
+            0 => NodeType::Client,
+            1 => NodeType::Drone,
+            _ => NodeType::Server,
+        }
+    }
+}
+
+/// Generates a packet for [`FUZZ_DRONE`], of any kind and with any routing header.
+/// Half of the routes pass through the drone so that forwarding is exercised too, the others are arbitrary,
+/// including empty routes and `hop_index` past the end.
+pub fn arbitrary_packet(rng: &mut FuzzRng) -> Packet {
+    let hops: Vec<NodeId> = if rng.chance(2) {
+        let mut hops = vec![FUZZ_NEIGHBOURS[rng.below(3) as usize], FUZZ_DRONE];
+        hops.extend((0..rng.below(3)).map(|_| rng.id()));
+        hops
+    } else {
+        (0..rng.below(6)).map(|_| rng.id()).collect()
+    };
+    let hop_index = if rng.chance(4) {
+        rng.below(hops.len() as u64 + 2) as usize
+    } else {
+        1
+    };
+    let routing_header = SourceRoutingHeader { hop_index, hops };
+
+    let pack_type = match rng.below(5) {
+        0 => {
+            let mut data = [0; 128];
+            for byte in data.iter_mut() {
+                *byte = rng.next_u64() as u8;
+            }
+            PacketType::MsgFragment(Fragment {
+                fragment_index: rng.below(8),
+                total_n_fragments: rng.below(8),
+                // Lengths above 128 are invalid, but a drone must not choke on them
+                length: rng.below(256) as u8,
+                data,
+            })
+        }
+        1 => PacketType::Ack(Ack {
+            fragment_index: rng.below(8),
+        }),
+        2 => PacketType::Nack(Nack {
+            fragment_index: rng.below(8),
+            nack_type: match rng.below(4) {
+                0 => NackType::ErrorInRouting(rng.id()),
+                1 => NackType::DestinationIsDrone,
+                2 => NackType::Dropped,
+                _ => NackType::UnexpectedRecipient(rng.id()),
+            },
+        }),
+        3 => PacketType::FloodRequest(FloodRequest {
+            flood_id: rng.below(4),
+            initiator_id: rng.id(),
+            path_trace: (0..rng.below(4))
+                .map(|_| (rng.id(), rng.node_type()))
+                .collect(),
+        }),
+        _ => PacketType::FloodResponse(FloodResponse {
+            flood_id: rng.below(4),
+            path_trace: (0..rng.below(4))
+                .map(|_| (rng.id(), rng.node_type()))
+                .collect(),
+        }),
+    };
+
+    Packet {
+        pack_type,
+        routing_header,
+        session_id: rng.below(1000),
+    }
+}
+
+/// Measure decreased by every shrinking step: route length, counters and ids, payload bytes, ...
+/// `Dropped` is the simplest `NackType` and a fragment is simplest when it is the only one.
+fn shrink_size(packet: &Packet) -> u128 {
+    let header = &packet.routing_header;
+    let base = header.hops.len() as u128 + header.hop_index as u128 + packet.session_id as u128;
+    base + match &packet.pack_type {
+        PacketType::MsgFragment(fragment) => {
+            fragment.data.iter().filter(|byte| **byte != 0).count() as u128
+                + fragment.length as u128
+                + fragment.fragment_index as u128
+                + fragment.total_n_fragments.abs_diff(1) as u128
+        }
+        PacketType::Ack(ack) => ack.fragment_index as u128,
+        PacketType::Nack(nack) => {
+            nack.fragment_index as u128 + u128::from(!matches!(nack.nack_type, NackType::Dropped))
+        }
+        PacketType::FloodRequest(request) => {
+            request.path_trace.len() as u128 + request.flood_id as u128
+        }
+        PacketType::FloodResponse(response) => {
+            response.path_trace.len() as u128 + response.flood_id as u128
+        }
+    }
+}
+
+/// Packets simpler than `packet`, tried in order when shrinking a failure.
+/// Only the candidates with a smaller [`shrink_size`] are kept, so that shrinking always ends.
+pub fn shrink_candidates(packet: &Packet) -> Vec<Packet> {
+    let size = shrink_size(packet);
+    let mut candidates = Vec::new();
+    let mut push = |candidate: Packet| {
+        if shrink_size(&candidate) < size && !candidates.contains(&candidate) {
+            candidates.push(candidate);
+        }
+    };
+
+    let header = &packet.routing_header;
+    for i in (0..header.hops.len()).rev() {
+        let mut candidate = packet.clone();
+        candidate.routing_header.hops.remove(i);
+        candidate.routing_header.hop_index =
+            header.hop_index.min(candidate.routing_header.hops.len());
+        push(candidate);
+    }
+    if header.hop_index > 0 {
+        let mut candidate = packet.clone();
+        candidate.routing_header.hop_index -= 1;
+        push(candidate);
+    }
+    if packet.session_id > 0 {
+        push(Packet {
+            session_id: 0,
+            ..packet.clone()
+        });
+    }
+
+    let with_type = |pack_type: PacketType| Packet {
+        pack_type,
+        ..packet.clone()
+    };
+    match &packet.pack_type {
+        PacketType::MsgFragment(fragment) => {
+            push(with_type(PacketType::MsgFragment(Fragment {
+                data: [0; 128],
+                ..fragment.clone()
+            })));
+            push(with_type(PacketType::MsgFragment(Fragment {
+                length: fragment.length.min(128) / 2,
+                ..fragment.clone()
+            })));
+            push(with_type(PacketType::MsgFragment(Fragment {
+                fragment_index: 0,
+                total_n_fragments: 1,
+                ..fragment.clone()
+            })));
+        }
+        PacketType::Ack(ack) if ack.fragment_index > 0 => {
+            push(with_type(PacketType::Ack(Ack { fragment_index: 0 })));
+        }
+        PacketType::Nack(nack) => {
+            push(with_type(PacketType::Nack(Nack {
+                fragment_index: 0,
+                ..nack.clone()
+            })));
+            push(with_type(PacketType::Nack(Nack {
+                nack_type: NackType::Dropped,
+                ..nack.clone()
+            })));
+        }
+        PacketType::FloodRequest(request) => {
+            for i in (0..request.path_trace.len()).rev() {
+                let mut request = request.clone();
+                request.path_trace.remove(i);
+                push(with_type(PacketType::FloodRequest(request)));
+            }
+            push(with_type(PacketType::FloodRequest(FloodRequest {
+                flood_id: 0,
+                ..request.clone()
+            })));
+        }
+        PacketType::FloodResponse(response) => {
+            for i in (0..response.path_trace.len()).rev() {
+                let mut response = response.clone();
+                response.path_trace.remove(i);
+                push(with_type(PacketType::FloodResponse(response)));
+            }
+            push(with_type(PacketType::FloodResponse(FloodResponse {
+                flood_id: 0,
+                ..response.clone()
+            })));
+        }
+        PacketType::Ack(_) => {}
+    }
+    candidates
+}
+
+/// Checks what a drone emitted after receiving `packet` alone:
+/// - it did not panic
+/// - every packet went to the neighbour at `hops[hop_index]` (flood requests, which have no route, excepted)
+/// - every packet sent was notified with `PacketSent`
+/// - the packet was forwarded, answered, dropped or given to the SC: something happened
+/// - only fragments are dropped
+pub fn check_trace(packet: &Packet, trace: &Trace) -> Result<(), String> {
+    if let Some(panic) = &trace.panic {
+        return Err(format!("the drone panicked: {}", panic));
+    }
+
+    let mut sent_events: Vec<&Packet> = trace
+        .events
+        .iter()
+        .filter_map(|event| match event {
+            DroneEvent::PacketSent(packet) => Some(packet),
+            _ => None,
+        })
+        .collect();
+    for (to, packets) in &trace.sent {
+        for sent in packets {
+            let header = &sent.routing_header;
+            let routed = matches!(sent.pack_type, PacketType::FloodRequest(_))
+                || header.hops.get(header.hop_index) == Some(to);
+            if !routed {
+                return Err(format!(
+                    "{} was sent to {}, which is not its next hop",
+                    PacketSummary(sent),
+                    to
+                ));
+            }
+            match sent_events.iter().position(|event| *event == sent) {
+                Some(pos) => {
+                    sent_events.remove(pos);
+                }
+                None => {
+                    return Err(format!(
+                        "{} was sent to {} without a PacketSent event",
+                        PacketSummary(sent),
+                        to
+                    ))
+                }
+            }
+        }
+    }
+
+    let handled = !trace.sent.is_empty()
+        || trace.events.iter().any(|event| {
+            matches!(
+                event,
+                DroneEvent::PacketDropped(_) | DroneEvent::ControllerShortcut(_)
+            )
+        });
+    if !handled {
+        return Err(
+            "the packet was neither forwarded, answered, dropped nor sent to the SC".to_string(),
+        );
+    }
+
+    let is_fragment = matches!(packet.pack_type, PacketType::MsgFragment(_));
+    if let Some(dropped) = trace
+        .events
+        .iter()
+        .find(|event| matches!(event, DroneEvent::PacketDropped(_)) && !is_fragment)
+    {
+        return Err(format!(
+            "only fragments can be dropped, got {}",
+            EventSummary(dropped)
+        ));
+    }
+    Ok(())
+}
+
+/// Runs every packet on its own drone built by `factory`, returns the result of [`check_trace`] for each.
+pub fn check_packets(
+    factory: DroneFactory,
+    pdr: f32,
+    packets: &[Packet],
+) -> Vec<Result<(), String>> {
+    let scenarios: Vec<Scenario> = packets
+        .iter()
+        .map(|packet| {
+            Scenario::new(FUZZ_DRONE, &FUZZ_NEIGHBOURS)
+                .pdr(pdr)
+                .packet(packet.clone())
+        })
+        .collect();
+    Scenario::record_all(&scenarios, factory)
+        .iter()
+        .zip(packets)
+        .map(|(trace, packet)| check_trace(packet, trace))
+        .collect()
+}
+
+/// Shrinks a failing `packet` as long as one of its [`shrink_candidates`] fails too.
+/// Returns the smallest failing packet with its error.
+pub fn shrink(factory: DroneFactory, pdr: f32, packet: Packet, error: String) -> (Packet, String) {
+    let (mut packet, mut error) = (packet, error);
+    let mut tried = HashSet::new();
+    loop {
+        let candidates: Vec<Packet> = shrink_candidates(&packet)
+            .into_iter()
+            .filter(|candidate| tried.insert(format!("{:?}", candidate)))
+            .collect();
+        let failure = check_packets(factory, pdr, &candidates)
+            .into_iter()
+            .zip(candidates)
+            .find_map(|(result, candidate)| result.err().map(|error| (candidate, error)));
+        match failure {
+            Some((candidate, candidate_error)) => {
+                packet = candidate;
+                error = candidate_error;
+            }
+            None => return (packet, error),
+        }
+    }
+}
+
+/// Sends `cases` packets generated from `seed` to drones built by `factory`, each on a fresh drone.
+/// The first failing packet is shrunk, then reported with the seed that generated it.
+pub fn fuzz_packets(factory: DroneFactory, pdr: f32, seed: u64, cases: usize) {
+    let mut rng = FuzzRng::new(seed);
+    let packets: Vec<Packet> = (0..cases).map(|_| arbitrary_packet(&mut rng)).collect();
+    let failure = check_packets(factory, pdr, &packets)
+        .into_iter()
+        .zip(packets)
+        .find_map(|(result, packet)| result.err().map(|error| (packet, error)));
+
+    if let Some((original, error)) = failure {
+        let (shrunk, shrunk_error) = shrink(factory, pdr, original.clone(), error);
+        panic!(
+            "Property violated by D({}) with neighbours {:?}: {}\n\
+             Minimal packet: {}\n\
+             Original packet: {}\n\
+             Replay with {}={}",
+            FUZZ_DRONE,
+            FUZZ_NEIGHBOURS,
+            shrunk_error,
+            PacketSummary(&shrunk),
+            PacketSummary(&original),
+            SEED_ENV_VAR,
+            seed
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use std::any::Any;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Extracts the message of a panic payload, as printed by the default panic hook.
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "Box<dyn Any>".to_string()
+    }
+}
+
+/// Why a drone thread did not end cleanly, see [`join_until`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum JoinError {
+    StillRunning,
+    /// Message of the panic.
+    Panicked(String),
+}
+
+/// Waits for a drone thread to end until `deadline`. The thread is left running if it has not ended by then.
+pub fn join_until(handle: JoinHandle<()>, deadline: Instant) -> Result<(), JoinError> {
+    while !handle.is_finished() {
+        if Instant::now() > deadline {
+            return Err(JoinError::StillRunning);
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    handle
+        .join()
+        .map_err(|payload| JoinError::Panicked(panic_message(&*payload)))
+}
+
+/// Waits for a drone thread to end, panics if it is still running after `deadline` or if it panicked.
+pub fn assert_exits_within(handle: JoinHandle<()>, deadline: Duration) {
+    match join_until(handle, Instant::now() + deadline) {
+        Ok(()) => {}
+        Err(JoinError::StillRunning) => panic!(
+            "The drone thread is still running {:?} after the deadline",
+            deadline
+        ),
+        Err(JoinError::Panicked(msg)) => panic!("The drone thread panicked: {}", msg),
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::BTreeMap;
+use wg_internal::network::{NodeId, SourceRoutingHeader};
+use wg_internal::packet::{Fragment, Packet, PacketType};
+
+/// Payload bytes carried by a fragment.
+pub const FRAGMENT_SIZE: usize = 128;
+
+/// Splits `message` into fragments of [`FRAGMENT_SIZE`] bytes sharing `session_id`, all sent along `hops`.
+/// The last fragment carries the remaining bytes, an empty message is a single empty fragment.
+pub fn fragment_message(session_id: u64, message: &[u8], hops: Vec<NodeId>) -> Vec<Packet> {
+    let chunks: Vec<&[u8]> = if message.is_empty() {
+        vec![&[]]
+    } else {
+        message.chunks(FRAGMENT_SIZE).collect()
+    };
+    let total_n_fragments = chunks.len() as u64;
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(index, chunk)| {
+            let mut data = [0; FRAGMENT_SIZE];
+            data[..chunk.len()].copy_from_slice(chunk);
+            Packet::new_fragment(
+                SourceRoutingHeader::new(hops.clone(), 1),
+                session_id,
+                Fragment {
+                    fragment_index: index as u64,
+                    total_n_fragments,
+                    length: chunk.len() as u8,
+                    data,
+                },
+            )
+        })
+        .collect()
+}
+
+/// Fragments of a single message, collected in any order.
+#[derive(Debug, Clone, Default)]
+pub struct Reassembler {
+    session_id: Option<u64>,
+    total_n_fragments: Option<u64>,
+    fragments: BTreeMap<u64, Fragment>,
+}
+
+impl Reassembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the fragment carried by `packet`.
+    /// Panics if it is not a fragment, if it does not belong to the same message as the previous ones or if its index
+    /// was already received.
+    pub fn push(&mut self, packet: &Packet) {
+        let PacketType::MsgFragment(fragment) = &packet.pack_type else {
+            panic!("expected a fragment, got {:?}", packet.pack_type);
+        };
+        let session_id = *self.session_id.get_or_insert(packet.session_id);
+        assert_eq!(
+            packet.session_id, session_id,
+            "fragment {} belongs to another session",
+            fragment.fragment_index
+        );
+        let total = *self
+            .total_n_fragments
+            .get_or_insert(fragment.total_n_fragments);
+        assert_eq!(
+            fragment.total_n_fragments, total,
+            "fragment {} disagrees on the number of fragments",
+            fragment.fragment_index
+        );
+        assert!(
+            fragment.fragment_index < total,
+            "fragment index {} is out of range, the message has {} fragments",
+            fragment.fragment_index,
+            total
+        );
+        assert!(
+            (fragment.length as usize) <= FRAGMENT_SIZE,
+            "fragment {} has length {}",
+            fragment.fragment_index,
+            fragment.length
+        );
+        let duplicate = self
+            .fragments
+            .insert(fragment.fragment_index, fragment.clone())
+            .is_some();
+        assert!(
+            !duplicate,
+            "fragment {} was received more than once",
+            fragment.fragment_index
+        );
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.total_n_fragments == Some(self.fragments.len() as u64)
+    }
+
+    /// Indices of the fragments not received yet.
+    pub fn missing(&self) -> Vec<u64> {
+        (0..self.total_n_fragments.unwrap_or(0))
+            .filter(|index| !self.fragments.contains_key(index))
+            .collect()
+    }
+
+    /// The message, once every fragment has been received.
+    pub fn message(&self) -> Option<Vec<u8>> {
+        if !self.is_complete() {
+            return None;
+        }
+        Some(
+            self.fragments
+                .values()
+                .flat_map(|fragment| fragment.data[..fragment.length as usize].iter().copied())
+                .collect(),
+        )
+    }
+}
@@ -0,0 +1,15 @@
+pub mod config;
+pub mod controller;
+pub mod diff;
+pub mod differential;
+pub mod discovery;
+pub mod fuzz;
+pub mod liveness;
+pub mod message;
+pub mod packet_diff;
+pub mod recorder;
+pub mod seed;
+pub mod stats;
+pub mod timing;
+pub mod topology;
+pub mod validate;
@@ -0,0 +1,219 @@
+use std::fmt::{self, Debug};
+use wg_internal::controller::DroneEvent;
+use wg_internal::packet::{Packet, PacketType};
+
+/// Compact `Display` of a packet: the fragment payload is left out, everything else is printed as in `Debug`.
+pub struct PacketSummary<'a>(pub &'a Packet);
+
+impl fmt::Display for PacketSummary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let packet = self.0;
+        let pack_type = match &packet.pack_type {
+            PacketType::MsgFragment(fragment) => format!(
+                "MsgFragment(Fragment {{ fragment_index: {}, total_n_fragments: {}, length: {}, data: .. }})",
+                fragment.fragment_index, fragment.total_n_fragments, fragment.length
+            ),
+            other => format!("{:?}", other),
+        };
+        write!(
+            f,
+            "{} {{ hop_index: {}, hops: {:?}, session_id: {} }}",
+            pack_type,
+            packet.routing_header.hop_index,
+            packet.routing_header.hops,
+            packet.session_id
+        )
+    }
+}
+
+/// Compact `Display` of an event, see [`PacketSummary`].
+pub struct EventSummary<'a>(pub &'a DroneEvent);
+
+impl fmt::Display for EventSummary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}({})",
+            event_name(self.0),
+            PacketSummary(event_packet(self.0))
+        )
+    }
+}
+
+fn variant_name(pack_type: &PacketType) -> &'static str {
+    match pack_type {
+        PacketType::MsgFragment(_) => "MsgFragment",
+        PacketType::Ack(_) => "Ack",
+        PacketType::Nack(_) => "Nack",
+        PacketType::FloodRequest(_) => "FloodRequest",
+        PacketType::FloodResponse(_) => "FloodResponse",
+    }
+}
+
+fn event_name(event: &DroneEvent) -> &'static str {
+    match event {
+        DroneEvent::PacketSent(_) => "PacketSent",
+        DroneEvent::PacketDropped(_) => "PacketDropped",
+        DroneEvent::ControllerShortcut(_) => "ControllerShortcut",
+    }
+}
+
+fn event_packet(event: &DroneEvent) -> &Packet {
+    match event {
+        DroneEvent::PacketSent(packet)
+        | DroneEvent::PacketDropped(packet)
+        | DroneEvent::ControllerShortcut(packet) => packet,
+    }
+}
+
+fn check<T: PartialEq + Debug + ?Sized>(
+    diffs: &mut Vec<String>,
+    field: &str,
+    actual: &T,
+    expected: &T,
+) {
+    if actual != expected {
+        diffs.push(format!("{}: {:?} != {:?}", field, actual, expected));
+    }
+}
+
+fn check_variant(diffs: &mut Vec<String>, field: &str, actual: &str, expected: &str) {
+    if actual != expected {
+        diffs.push(format!("{}: {} != {}", field, actual, expected));
+    }
+}
+
+/// Returns one line per field that differs between `actual` and `expected`, formatted as `field: actual != expected`.
+/// Payloads are compared byte by byte and only reported when they differ.
+pub fn packet_diff(actual: &Packet, expected: &Packet) -> Vec<String> {
+    let mut diffs = Vec::new();
+    check_variant(
+        &mut diffs,
+        "pack_type",
+        variant_name(&actual.pack_type),
+        variant_name(&expected.pack_type),
+    );
+    check(
+        &mut diffs,
+        "hop_index",
+        &actual.routing_header.hop_index,
+        &expected.routing_header.hop_index,
+    );
+    check(
+        &mut diffs,
+        "hops",
+        &actual.routing_header.hops,
+        &expected.routing_header.hops,
+    );
+    check(
+        &mut diffs,
+        "session_id",
+        &actual.session_id,
+        &expected.session_id,
+    );
+
+    match (&actual.pack_type, &expected.pack_type) {
+        (PacketType::MsgFragment(a), PacketType::MsgFragment(e)) => {
+            check(
+                &mut diffs,
+                "fragment_index",
+                &a.fragment_index,
+                &e.fragment_index,
+            );
+            check(
+                &mut diffs,
+                "total_n_fragments",
+                &a.total_n_fragments,
+                &e.total_n_fragments,
+            );
+            check(&mut diffs, "length", &a.length, &e.length);
+            let differing: Vec<usize> = a
+                .data
+                .iter()
+                .zip(e.data.iter())
+                .enumerate()
+                .filter(|(_, (a, e))| a != e)
+                .map(|(i, _)| i)
+                .collect();
+            if let Some(&first) = differing.first() {
+                diffs.push(format!(
+                    "data: {} byte(s) differ, first at index {}: {} != {}",
+                    differing.len(),
+                    first,
+                    a.data[first],
+                    e.data[first]
+                ));
+            }
+        }
+        (PacketType::Ack(a), PacketType::Ack(e)) => {
+            check(
+                &mut diffs,
+                "fragment_index",
+                &a.fragment_index,
+                &e.fragment_index,
+            );
+        }
+        (PacketType::Nack(a), PacketType::Nack(e)) => {
+            check(
+                &mut diffs,
+                "fragment_index",
+                &a.fragment_index,
+                &e.fragment_index,
+            );
+            check(&mut diffs, "nack_type", &a.nack_type, &e.nack_type);
+        }
+        (PacketType::FloodRequest(a), PacketType::FloodRequest(e)) => {
+            check(&mut diffs, "flood_id", &a.flood_id, &e.flood_id);
+            check(&mut diffs, "initiator_id", &a.initiator_id, &e.initiator_id);
+            check(&mut diffs, "path_trace", &a.path_trace, &e.path_trace);
+        }
+        (PacketType::FloodResponse(a), PacketType::FloodResponse(e)) => {
+            check(&mut diffs, "flood_id", &a.flood_id, &e.flood_id);
+            check(&mut diffs, "path_trace", &a.path_trace, &e.path_trace);
+        }
+        // Different variants, already reported
+        _ => {}
+    }
+    diffs
+}
+
+/// Same as [`packet_diff`] for events: the variant first, then the carried packets.
+pub fn event_diff(actual: &DroneEvent, expected: &DroneEvent) -> Vec<String> {
+    let mut diffs = Vec::new();
+    check_variant(
+        &mut diffs,
+        "event",
+        event_name(actual),
+        event_name(expected),
+    );
+    diffs.extend(packet_diff(event_packet(actual), event_packet(expected)));
+    diffs
+}
+
+/// Message for `assert_packet_eq!`, `None` if the packets are equal.
+#[doc(hidden)]
+pub fn packet_mismatch(actual: &Packet, expected: &Packet) -> Option<String> {
+    if actual == expected {
+        return None;
+    }
+    Some(format!(
+        "assertion `actual == expected` failed, packets differ:\n  {}\nActual:   {}\nExpected: {}",
+        packet_diff(actual, expected).join("\n  "),
+        PacketSummary(actual),
+        PacketSummary(expected)
+    ))
+}
+
+/// Message for `assert_event_eq!`, `None` if the events are equal.
+#[doc(hidden)]
+pub fn event_mismatch(actual: &DroneEvent, expected: &DroneEvent) -> Option<String> {
+    if actual == expected {
+        return None;
+    }
+    Some(format!(
+        "assertion `actual == expected` failed, events differ:\n  {}\nActual:   {}\nExpected: {}",
+        event_diff(actual, expected).join("\n  "),
+        EventSummary(actual),
+        EventSummary(expected)
+    ))
+}
@@ -0,0 +1,132 @@
+use crossbeam::channel::Receiver;
+use std::fmt::Debug;
+use std::time::{Duration, Instant};
+
+/// Items (`Packet`s, `DroneEvent`s, ...) drained from a channel, with assertions on what arrived.
+///
+/// ```ignore
+/// let events = Recorder::record_n(net.events(), 2, timeout());
+/// events.assert_exactly(&[DroneEvent::PacketDropped(msg), DroneEvent::PacketSent(nack)]);
+/// assert_silent(net.events(), quiet());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Recorder<T> {
+    items: Vec<T>,
+}
+
+impl<T: Debug + PartialEq> Recorder<T> {
+    /// Records everything arriving on `recv` until `window` is elapsed.
+    pub fn record(recv: &Receiver<T>, window: Duration) -> Self {
+        Self::record_until(recv, usize::MAX, Instant::now() + window)
+    }
+
+    /// Records until `count` items arrived on `recv` or `timeout` is elapsed, whichever comes first.
+    pub fn record_n(recv: &Receiver<T>, count: usize, timeout: Duration) -> Self {
+        Self::record_until(recv, count, Instant::now() + timeout)
+    }
+
+    /// Records until nothing arrived on `recv` for `quiet` or `deadline` is elapsed, whichever comes first.
+    pub fn record_until_quiet(recv: &Receiver<T>, quiet: Duration, deadline: Duration) -> Self {
+        let deadline = Instant::now() + deadline;
+        let mut items = Vec::new();
+        loop {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match recv.recv_timeout(left.min(quiet)) {
+                Ok(item) => items.push(item),
+                Err(_) => break,
+            }
+        }
+        Self { items }
+    }
+
+    fn record_until(recv: &Receiver<T>, count: usize, deadline: Instant) -> Self {
+        let mut items = Vec::new();
+        while items.len() < count {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match recv.recv_timeout(left) {
+                Ok(item) => items.push(item),
+                Err(_) => break,
+            }
+        }
+        Self { items }
+    }
+
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    pub fn into_items(self) -> Vec<T> {
+        self.items
+    }
+
+    /// Asserts the recorded items are exactly `expected`, in any order (duplicates included).
+    pub fn assert_exactly(&self, expected: &[T]) {
+        let (missing, unexpected) = self.multiset_diff(expected);
+        assert!(
+            missing.is_empty() && unexpected.is_empty(),
+            "Recorded items do not match the expected ones.\n\
+             Missing: `{:?}`\n\
+             Unexpected: `{:?}`\n\
+             Recorded: `{:?}`",
+            missing,
+            unexpected,
+            self.items
+        );
+    }
+
+    /// Asserts every item of `expected` was recorded, in any order. Other items may have been recorded too.
+    pub fn assert_contains(&self, expected: &[T]) {
+        let (missing, _) = self.multiset_diff(expected);
+        assert!(
+            missing.is_empty(),
+            "Expected items were not recorded.\n\
+             Missing: `{:?}`\n\
+             Recorded: `{:?}`",
+            missing,
+            self.items
+        );
+    }
+
+    /// Asserts the items of `expected` were recorded in this order. Other items may be interleaved.
+    pub fn assert_contains_in_order(&self, expected: &[T]) {
+        let mut recorded = self.items.iter();
+        for (i, item) in expected.iter().enumerate() {
+            assert!(
+                recorded.any(|r| r == item),
+                "Expected item #{} was not recorded after the previous ones.\n\
+                 Missing: `{:?}`\n\
+                 Expected order: `{:?}`\n\
+                 Recorded: `{:?}`",
+                i,
+                item,
+                expected,
+                self.items
+            );
+        }
+    }
+
+    /// Returns the expected items that were not recorded and the recorded items that were not expected.
+    fn multiset_diff<'a>(&'a self, expected: &'a [T]) -> (Vec<&'a T>, Vec<&'a T>) {
+        let mut unexpected: Vec<&T> = self.items.iter().collect();
+        let mut missing = Vec::new();
+        for item in expected {
+            match unexpected.iter().position(|r| *r == item) {
+                Some(pos) => {
+                    unexpected.remove(pos);
+                }
+                None => missing.push(item),
+            }
+        }
+        (missing, unexpected)
+    }
+}
+
+/// Panics if anything arrives on `recv` within `window`.
+pub fn assert_silent<T: Debug>(recv: &Receiver<T>, window: Duration) {
+    if let Ok(item) = recv.recv_timeout(window) {
+        panic!(
+            "Nothing was expected within {:?}, received: `{:?}`",
+            window, item
+        );
+    }
+}
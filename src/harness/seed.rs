@@ -0,0 +1,57 @@
+use std::env;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+use wg_internal::network::NodeId;
+
+/// Environment variable holding the seed of the seeded tests.
+pub const SEED_ENV_VAR: &str = "RUSTEZE_SEED";
+
+/// Seed of the current test: the value of [`SEED_ENV_VAR`] if set, a time-based one otherwise.
+pub fn test_seed() -> u64 {
+    match env::var(SEED_ENV_VAR) {
+        Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+            panic!(
+                "{} must be an unsigned integer, got `{}`",
+                SEED_ENV_VAR, value
+            )
+        }),
+        Err(_) => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or_default(),
+    }
+}
+
+/// Seed given to drone `id`, so that drones of the same network do not drop the same packets.
+pub fn drone_seed(seed: u64, id: NodeId) -> u64 {
+    seed ^ (id as u64 + 1).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+}
+
+/// Prints the seed if the test fails while the guard is alive, so that the failure can be replayed.
+///
+/// ```ignore
+/// let seed = test_seed();
+/// let _guard = SeedGuard::new(seed);
+/// ```
+#[derive(Debug)]
+pub struct SeedGuard {
+    seed: u64,
+}
+
+impl SeedGuard {
+    pub fn new(seed: u64) -> Self {
+        Self { seed }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Drop for SeedGuard {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            eprintln!("Replay this test with {}={}", SEED_ENV_VAR, self.seed);
+        }
+    }
+}
@@ -0,0 +1,26 @@
+/// Half-width of the acceptance interval, in standard deviations.
+/// A drone dropping with exactly the configured probability fails the check about once every 100 000 runs.
+pub const Z_SCORE: f64 = 4.42;
+
+/// Interval of the number of successes expected out of `trials` independent draws with probability `p`,
+/// using the normal approximation of the binomial distribution.
+pub fn binomial_interval(trials: u64, p: f64, z: f64) -> (f64, f64) {
+    let mean = trials as f64 * p;
+    let std_dev = (trials as f64 * p * (1.0 - p)).sqrt();
+    (mean - z * std_dev, mean + z * std_dev)
+}
+
+/// Panics if `observed` successes out of `trials` are not compatible with a success probability `p`.
+pub fn assert_binomial(observed: u64, trials: u64, p: f64) {
+    let (low, high) = binomial_interval(trials, p, Z_SCORE);
+    assert!(
+        (low..=high).contains(&(observed as f64)),
+        "Observed {} out of {} ({:.3}), expected between {:.0} and {:.0} for a probability of {}",
+        observed,
+        trials,
+        observed as f64 / trials as f64,
+        low.max(0.0),
+        high.min(trials as f64),
+        p
+    );
+}
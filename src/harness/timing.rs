@@ -0,0 +1,190 @@
+use std::cell::Cell;
+use std::env;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// Multiplies every duration of the policy, e.g. `2.0` on a loaded CI machine.
+pub const SCALE_ENV_VAR: &str = "RUSTEZE_TIMING_SCALE";
+/// Overrides [`Timing::timeout`], in milliseconds.
+pub const TIMEOUT_ENV_VAR: &str = "RUSTEZE_TIMEOUT_MS";
+/// Overrides [`Timing::quiet`], in milliseconds.
+pub const QUIET_ENV_VAR: &str = "RUSTEZE_QUIET_MS";
+/// Overrides [`Timing::settle`], in milliseconds.
+pub const SETTLE_ENV_VAR: &str = "RUSTEZE_SETTLE_MS";
+
+static CURRENT: OnceLock<Timing> = OnceLock::new();
+
+thread_local! {
+    /// Policy of the current test, set by [`Timing::scoped`].
+    static SCOPED: Cell<Option<Timing>> = const { Cell::new(None) };
+}
+
+/// How long the tests wait for the drones.
+/// Every receive and sleep of the crate goes through the policy returned by [`Timing::current`].
+///
+/// ```ignore
+/// // For the rest of the current test only
+/// let _timing = Timing::from_env().scale(3.0).quiet(Duration::from_millis(50)).scoped();
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timing {
+    timeout: Duration,
+    quiet: Duration,
+    settle: Duration,
+    scale: f64,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_millis(400),
+            quiet: Duration::from_millis(200),
+            settle: Duration::from_millis(100),
+            scale: 1.0,
+        }
+    }
+}
+
+impl Timing {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The defaults, overridden by the environment variables that are set.
+    pub fn from_env() -> Self {
+        let mut timing = Self::default();
+        if let Some(ms) = env_var(TIMEOUT_ENV_VAR) {
+            timing.timeout = Duration::from_millis(ms);
+        }
+        if let Some(ms) = env_var(QUIET_ENV_VAR) {
+            timing.quiet = Duration::from_millis(ms);
+        }
+        if let Some(ms) = env_var(SETTLE_ENV_VAR) {
+            timing.settle = Duration::from_millis(ms);
+        }
+        if let Some(scale) = env_var(SCALE_ENV_VAR) {
+            timing = timing.scale(scale);
+        }
+        timing
+    }
+
+    /// Longest wait for a packet or an event that is expected to arrive.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Window in which nothing must arrive, for the checks expecting silence.
+    pub fn quiet(mut self, quiet: Duration) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Time given to a drone to process a command before the test goes on.
+    pub fn settle(mut self, settle: Duration) -> Self {
+        self.settle = settle;
+        self
+    }
+
+    pub fn scale(mut self, scale: f64) -> Self {
+        assert!(
+            scale.is_finite() && scale > 0.0,
+            "the timing scale must be positive, got {}",
+            scale
+        );
+        self.scale = scale;
+        self
+    }
+
+    /// Makes this policy the one used by every test of the process.
+    /// Fails, returning `self`, if a policy is already in use. Since the tests run in parallel, there is no telling which
+    /// one uses the policy first: prefer [`Timing::scoped`] within a test, or the environment variables.
+    pub fn install(self) -> Result<(), Self> {
+        CURRENT.set(self)
+    }
+
+    /// Makes this policy the one used by the current thread, i.e. the current test, until the guard is dropped.
+    pub fn scoped(self) -> TimingGuard {
+        TimingGuard {
+            previous: SCOPED.with(|scoped| scoped.replace(Some(self))),
+        }
+    }
+
+    /// The policy of the current test if [`Timing::scoped`] was used, otherwise the installed one, or
+    /// [`Timing::from_env`] if none was installed before the first use.
+    pub fn current() -> Timing {
+        SCOPED
+            .with(Cell::get)
+            .unwrap_or_else(|| *CURRENT.get_or_init(Self::from_env))
+    }
+
+    pub fn timeout_duration(&self) -> Duration {
+        self.timeout.mul_f64(self.scale)
+    }
+
+    pub fn quiet_duration(&self) -> Duration {
+        self.quiet.mul_f64(self.scale)
+    }
+
+    pub fn settle_duration(&self) -> Duration {
+        self.settle.mul_f64(self.scale)
+    }
+}
+
+/// Restores the previous policy of the thread when dropped, see [`Timing::scoped`].
+#[derive(Debug)]
+pub struct TimingGuard {
+    previous: Option<Timing>,
+}
+
+impl Drop for TimingGuard {
+    fn drop(&mut self) {
+        SCOPED.with(|scoped| scoped.set(self.previous));
+    }
+}
+
+fn env_var<T: FromStr>(name: &str) -> Option<T> {
+    let value = env::var(name).ok()?;
+    Some(
+        value
+            .trim()
+            .parse()
+            .unwrap_or_else(|_| panic!("{} has an invalid value: `{}`", name, value)),
+    )
+}
+
+/// Scaled timeout of the current policy, see [`Timing::timeout`].
+pub fn timeout() -> Duration {
+    Timing::current().timeout_duration()
+}
+
+/// Scaled quiet window of the current policy, see [`Timing::quiet`].
+pub fn quiet() -> Duration {
+    Timing::current().quiet_duration()
+}
+
+/// Scaled settle time of the current policy, see [`Timing::settle`].
+pub fn settle() -> Duration {
+    Timing::current().settle_duration()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scoped_policy_is_restored() {
+        let before = Timing::current();
+        {
+            let _timing = before.scale(3.0).scoped();
+            assert_eq!(timeout(), before.timeout_duration() * 3);
+            {
+                let _inner = Timing::new().settle(Duration::from_millis(7)).scoped();
+                assert_eq!(settle(), Duration::from_millis(7));
+            }
+            assert_eq!(timeout(), before.timeout_duration() * 3);
+        }
+        assert_eq!(Timing::current(), before);
+    }
+}
@@ -0,0 +1,507 @@
+use crossbeam::channel::{unbounded, Receiver, RecvTimeoutError, Sender};
+use std::collections::HashMap;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use wg_internal::controller::{DroneCommand, DroneEvent};
+use wg_internal::drone::Drone;
+use wg_internal::network::NodeId;
+use wg_internal::packet::Packet;
+
+use crate::liveness::{assert_exits_within, join_until, JoinError};
+use crate::seed::{drone_seed, SeedGuard};
+use crate::timing::timeout;
+use crate::validate::{ValidationError, Violation};
+use crate::SeededDrone;
+
+/// Builds a boxed drone, with the same arguments as [`Drone::new`].
+/// Used to mix implementations of different groups inside a single network.
+pub type DroneFactory = fn(
+    NodeId,
+    Sender<DroneEvent>,
+    Receiver<DroneCommand>,
+    Receiver<Packet>,
+    HashMap<NodeId, Sender<Packet>>,
+    f32,
+) -> Box<dyn Drone + Send>;
+
+/// [`DroneFactory`] for the implementation `T`, usable as `boxed_drone::<T>`.
+pub fn boxed_drone<T: Drone + Send + 'static>(
+    id: NodeId,
+    controller_send: Sender<DroneEvent>,
+    controller_recv: Receiver<DroneCommand>,
+    packet_recv: Receiver<Packet>,
+    packet_send: HashMap<NodeId, Sender<Packet>>,
+    pdr: f32,
+) -> Box<dyn Drone + Send> {
+    Box::new(T::new(
+        id,
+        controller_send,
+        controller_recv,
+        packet_recv,
+        packet_send,
+        pdr,
+    ))
+}
+
+/// A drone implementation with a readable name, used to report which combination failed.
+#[derive(Clone, Copy)]
+pub struct DroneImpl {
+    pub name: &'static str,
+    pub factory: DroneFactory,
+}
+
+impl DroneImpl {
+    pub fn of<T: Drone + Send + 'static>() -> Self {
+        Self {
+            name: std::any::type_name::<T>(),
+            factory: boxed_drone::<T>,
+        }
+    }
+}
+
+/// Role of a node inside a test topology.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeRole {
+    Client,
+    Drone { pdr: f32 },
+    Server,
+}
+
+/// Declarative description of a test network.
+/// Drones are instantiated and spawned when the topology is built, clients and servers become [`Probe`]s driven by the test.
+///
+/// ```ignore
+/// // C(1) <-> D(11) <-> D(12) <-> S(21)
+/// let net = TopologyBuilder::new()
+///     .client(1)
+///     .drone(11, 0.0)
+///     .drone(12, 0.0)
+///     .server(21)
+///     .edge(1, 11)
+///     .edge(11, 12)
+///     .edge(12, 21)
+///     .spawn::<MyDrone>();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TopologyBuilder {
+    nodes: Vec<(NodeId, NodeRole)>,
+    edges: Vec<(NodeId, NodeId)>,
+}
+
+impl TopologyBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn client(mut self, id: NodeId) -> Self {
+        self.nodes.push((id, NodeRole::Client));
+        self
+    }
+
+    pub fn drone(mut self, id: NodeId, pdr: f32) -> Self {
+        self.nodes.push((id, NodeRole::Drone { pdr }));
+        self
+    }
+
+    pub fn server(mut self, id: NodeId) -> Self {
+        self.nodes.push((id, NodeRole::Server));
+        self
+    }
+
+    /// Adds a bidirectional link between `a` and `b`.
+    pub fn edge(mut self, a: NodeId, b: NodeId) -> Self {
+        self.edges.push((a, b));
+        self
+    }
+
+    pub fn nodes(&self) -> &[(NodeId, NodeRole)] {
+        &self.nodes
+    }
+
+    pub fn edges(&self) -> &[(NodeId, NodeId)] {
+        &self.edges
+    }
+
+    pub fn role(&self, id: NodeId) -> Option<NodeRole> {
+        self.nodes
+            .iter()
+            .find(|(node, _)| *node == id)
+            .map(|(_, role)| *role)
+    }
+
+    /// Whether `a` and `b` are linked, in either direction.
+    pub fn has_edge(&self, a: NodeId, b: NodeId) -> bool {
+        self.edges
+            .iter()
+            .any(|&edge| edge == (a, b) || edge == (b, a))
+    }
+
+    /// Returns the ids linked to `id`, in the order the edges were declared.
+    pub fn neighbours(&self, id: NodeId) -> Vec<NodeId> {
+        self.edges
+            .iter()
+            .filter_map(|&(a, b)| {
+                if a == id {
+                    Some(b)
+                } else if b == id {
+                    Some(a)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Creates a channel for every node, spawns a `T` for every drone and returns the handles used by the test.
+    pub fn spawn<T: Drone + Send + 'static>(self) -> Network {
+        self.spawn_with(|_| boxed_drone::<T>)
+    }
+
+    /// Same as [`TopologyBuilder::spawn`], but the implementation of every drone is chosen by `factory_for`.
+    pub fn spawn_with(self, factory_for: impl Fn(NodeId) -> DroneFactory) -> Network {
+        self.spawn_drones(
+            None,
+            |id, event_send, command_recv, packet_recv, neighbours, pdr| {
+                factory_for(id)(id, event_send, command_recv, packet_recv, neighbours, pdr)
+            },
+        )
+    }
+
+    /// Same as [`TopologyBuilder::spawn`], but every drone is seeded with [`drone_seed`] before it runs.
+    /// `seed` is printed if the test fails, see [`SeededDrone`].
+    pub fn spawn_seeded<T: SeededDrone + Send + 'static>(self, seed: u64) -> Network {
+        self.spawn_drones(
+            Some(seed),
+            |id, event_send, command_recv, packet_recv, neighbours, pdr| {
+                let mut drone = T::new(id, event_send, command_recv, packet_recv, neighbours, pdr);
+                drone.set_seed(drone_seed(seed, id));
+                Box::new(drone)
+            },
+        )
+    }
+
+    fn spawn_drones(
+        self,
+        seed: Option<u64>,
+        build: impl Fn(
+            NodeId,
+            Sender<DroneEvent>,
+            Receiver<DroneCommand>,
+            Receiver<Packet>,
+            HashMap<NodeId, Sender<Packet>>,
+            f32,
+        ) -> Box<dyn Drone + Send>,
+    ) -> Network {
+        let malformed: Vec<Violation> = self
+            .violations()
+            .into_iter()
+            .filter(Violation::is_malformed)
+            .collect();
+        if !malformed.is_empty() {
+            panic!("{}", ValidationError(malformed));
+        }
+
+        let channels: HashMap<NodeId, (Sender<Packet>, Receiver<Packet>)> = self
+            .nodes
+            .iter()
+            .map(|(id, _)| (*id, unbounded()))
+            .collect();
+        let (event_send, event_recv) = unbounded();
+
+        let mut drones = HashMap::new();
+        let mut probes = HashMap::new();
+        for (id, role) in &self.nodes {
+            let neighbours: HashMap<NodeId, Sender<Packet>> = self
+                .neighbours(*id)
+                .into_iter()
+                .map(|n| {
+                    let (send, _) = channels
+                        .get(&n)
+                        .unwrap_or_else(|| panic!("edge {id} <-> {n} refers to an unknown node"));
+                    (n, send.clone())
+                })
+                .collect();
+            let (packet_send, packet_recv) = channels[id].clone();
+
+            match role {
+                NodeRole::Drone { pdr } => {
+                    let (command_send, command_recv) = unbounded();
+                    let mut drone = build(
+                        *id,
+                        event_send.clone(),
+                        command_recv,
+                        packet_recv,
+                        neighbours,
+                        *pdr,
+                    );
+                    // Spawn the drone's run method in a separate thread
+                    let thread = thread::spawn(move || {
+                        drone.run();
+                    });
+                    drones.insert(
+                        *id,
+                        DroneHandle {
+                            id: *id,
+                            packet_send: Some(packet_send),
+                            command_send,
+                            thread: Some(thread),
+                        },
+                    );
+                }
+                NodeRole::Client | NodeRole::Server => {
+                    probes.insert(
+                        *id,
+                        Probe {
+                            id: *id,
+                            packet_send,
+                            packet_recv,
+                            neighbours,
+                        },
+                    );
+                }
+            }
+        }
+
+        Network {
+            topology: self,
+            probes,
+            drones,
+            event_recv,
+            seed: seed.map(SeedGuard::new),
+        }
+    }
+}
+
+/// Test-side endpoint standing in for a client or a server.
+pub struct Probe {
+    id: NodeId,
+    /// Input channel of the probe, used by the SC to deliver shortcuts.
+    packet_send: Sender<Packet>,
+    packet_recv: Receiver<Packet>,
+    neighbours: HashMap<NodeId, Sender<Packet>>,
+}
+
+impl Probe {
+    pub fn id(&self) -> NodeId {
+        self.id
+    }
+
+    /// Sends `packet` to the neighbour `to`.
+    pub fn send_to(&self, to: NodeId, packet: Packet) {
+        self.neighbours
+            .get(&to)
+            .unwrap_or_else(|| panic!("node {} is not a neighbour of {}", to, self.id))
+            .send(packet)
+            .unwrap();
+    }
+
+    /// Sends `packet` to the node at `hops[hop_index]`, the one expected to handle it next.
+    pub fn send(&self, packet: Packet) {
+        let header = &packet.routing_header;
+        let next = *header
+            .hops
+            .get(header.hop_index)
+            .unwrap_or_else(|| panic!("packet has no hop at index {}", header.hop_index));
+        self.send_to(next, packet);
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Packet, RecvTimeoutError> {
+        self.packet_recv.recv_timeout(timeout)
+    }
+
+    pub fn receiver(&self) -> &Receiver<Packet> {
+        &self.packet_recv
+    }
+}
+
+/// Controller-side handles of a spawned drone.
+pub struct DroneHandle {
+    id: NodeId,
+    /// `None` once the drone has been crashed, so that its input channel can be closed.
+    packet_send: Option<Sender<Packet>>,
+    command_send: Sender<DroneCommand>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl DroneHandle {
+    /// Injects `packet` directly into the drone's input channel.
+    pub fn send(&self, packet: Packet) {
+        self.packet_sender().send(packet).unwrap();
+    }
+
+    pub fn command(&self, command: DroneCommand) {
+        self.command_send.send(command).unwrap();
+    }
+
+    pub fn packet_sender(&self) -> Sender<Packet> {
+        self.packet_send
+            .clone()
+            .unwrap_or_else(|| panic!("drone {} has been crashed", self.id))
+    }
+}
+
+/// A spawned [`TopologyBuilder`]: probes for clients and servers, handles for drones and the shared event channel.
+/// When dropped, every drone is told to crash and its input channel is closed so that its thread ends. The drones that
+/// panicked or are still running after [`timeout`] fail the test, or are printed to stderr if it already failed.
+pub struct Network {
+    topology: TopologyBuilder,
+    probes: HashMap<NodeId, Probe>,
+    drones: HashMap<NodeId, DroneHandle>,
+    event_recv: Receiver<DroneEvent>,
+    /// Prints the seed if the test fails.
+    seed: Option<SeedGuard>,
+}
+
+impl Network {
+    pub fn topology(&self) -> &TopologyBuilder {
+        &self.topology
+    }
+
+    pub fn probe(&self, id: NodeId) -> &Probe {
+        self.probes
+            .get(&id)
+            .unwrap_or_else(|| panic!("node {id} is not a client or a server"))
+    }
+
+    pub fn drone(&self, id: NodeId) -> &DroneHandle {
+        self.drones
+            .get(&id)
+            .unwrap_or_else(|| panic!("node {id} is not a drone"))
+    }
+
+    /// Seed the drones were spawned with, if the network comes from [`TopologyBuilder::spawn_seeded`].
+    pub fn seed(&self) -> Option<u64> {
+        self.seed.as_ref().map(SeedGuard::seed)
+    }
+
+    /// Events sent by every drone of the network.
+    pub fn events(&self) -> &Receiver<DroneEvent> {
+        &self.event_recv
+    }
+
+    /// Crashes drone `id` as the SC does: its neighbours remove it first, then it receives the `Crash` command.
+    /// The handles of the network stop sending packets to it, so its input channel is closed once the neighbours have removed it.
+    pub fn crash(&mut self, id: NodeId) {
+        for n in self.topology.neighbours(id) {
+            if let Some(drone) = self.drones.get(&n) {
+                drone.command(DroneCommand::RemoveSender(id));
+            }
+            if let Some(probe) = self.probes.get_mut(&n) {
+                probe.neighbours.remove(&id);
+            }
+        }
+        let drone = self
+            .drones
+            .get_mut(&id)
+            .unwrap_or_else(|| panic!("node {id} is not a drone"));
+        drone.command(DroneCommand::Crash);
+        drone.packet_send = None;
+    }
+
+    /// Input channel of node `id`, `None` if it is a crashed drone.
+    pub fn input(&self, id: NodeId) -> Option<Sender<Packet>> {
+        match (self.drones.get(&id), self.probes.get(&id)) {
+            (Some(drone), _) => drone.packet_send.clone(),
+            (_, Some(probe)) => Some(probe.packet_send.clone()),
+            _ => panic!("node {id} is not part of the network"),
+        }
+    }
+
+    /// Links `a` and `b` as the SC does: each of them is given a sender to the other with `AddSender`.
+    /// Connecting two nodes already linked only gives them the senders again, the link is not duplicated.
+    pub fn connect(&mut self, a: NodeId, b: NodeId) {
+        for (from, to) in [(a, b), (b, a)] {
+            let send = self
+                .input(to)
+                .unwrap_or_else(|| panic!("drone {to} has been crashed"));
+            if let Some(drone) = self.drones.get(&from) {
+                drone.command(DroneCommand::AddSender(to, send));
+            } else if let Some(probe) = self.probes.get_mut(&from) {
+                probe.neighbours.insert(to, send);
+            }
+        }
+        if !self.topology.has_edge(a, b) {
+            self.topology.edges.push((a, b));
+        }
+    }
+
+    /// Unlinks `a` and `b` as the SC does: each of them removes the other with `RemoveSender`.
+    pub fn disconnect(&mut self, a: NodeId, b: NodeId) {
+        for (from, to) in [(a, b), (b, a)] {
+            if let Some(drone) = self.drones.get(&from) {
+                drone.command(DroneCommand::RemoveSender(to));
+            } else if let Some(probe) = self.probes.get_mut(&from) {
+                probe.neighbours.remove(&to);
+            }
+        }
+        self.topology
+            .edges
+            .retain(|&edge| edge != (a, b) && edge != (b, a));
+    }
+
+    pub fn set_pdr(&self, id: NodeId, pdr: f32) {
+        self.drone(id).command(DroneCommand::SetPacketDropRate(pdr));
+    }
+
+    /// Panics if the thread of drone `id` is still running after `deadline` or if it panicked.
+    pub fn assert_exited(&mut self, id: NodeId, deadline: Duration) {
+        let thread = self
+            .drones
+            .get_mut(&id)
+            .and_then(|drone| drone.thread.take())
+            .unwrap_or_else(|| panic!("node {id} is not a running drone"));
+        assert_exits_within(thread, deadline);
+    }
+}
+
+impl Drop for Network {
+    fn drop(&mut self) {
+        // Every drone forgets its neighbours and crashes, and the handles let go of their senders: every input channel
+        // gets closed, so that no thread outlives the test
+        for (id, drone) in &mut self.drones {
+            for n in self.topology.neighbours(*id) {
+                let _ = drone.command_send.send(DroneCommand::RemoveSender(n));
+            }
+            let _ = drone.command_send.send(DroneCommand::Crash);
+            drone.packet_send = None;
+        }
+        for probe in self.probes.values_mut() {
+            probe.neighbours.clear();
+        }
+
+        let deadline = Instant::now() + timeout();
+        let mut ids: Vec<NodeId> = self.drones.keys().copied().collect();
+        ids.sort_unstable();
+        let mut failures = Vec::new();
+        for id in ids {
+            let Some(thread) = self
+                .drones
+                .get_mut(&id)
+                .and_then(|drone| drone.thread.take())
+            else {
+                continue;
+            };
+            match join_until(thread, deadline) {
+                Ok(()) => {}
+                Err(JoinError::StillRunning) => failures.push(format!(
+                    "D({id}): still running {:?} after the network was dropped",
+                    timeout()
+                )),
+                Err(JoinError::Panicked(msg)) => failures.push(format!("D({id}) panicked: {msg}")),
+            }
+        }
+        if failures.is_empty() {
+            return;
+        }
+        let msg = format!(
+            "Drone threads did not end cleanly:\n{}",
+            failures.join("\n")
+        );
+        if thread::panicking() {
+            // The test already failed, often because of a drone panic: its message must not be lost
+            eprintln!("{}", msg);
+        } else {
+            panic!("{}", msg);
+        }
+    }
+}
@@ -0,0 +1,498 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use wg_internal::config::Config;
+use wg_internal::network::NodeId;
+
+use crate::topology::{NodeRole, TopologyBuilder};
+
+/// A rule of the network-initialization protocol broken by a topology.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    /// More than one node is declared with this id.
+    DuplicateId(NodeId),
+    /// An edge refers to an id that is not declared.
+    UnknownNode {
+        edge: (NodeId, NodeId),
+        id: NodeId,
+    },
+    SelfLoop(NodeId),
+    /// The same link is declared more than once.
+    DuplicateEdge(NodeId, NodeId),
+    /// `from` lists `to` among its neighbours, but `to` does not list `from`.
+    AsymmetricEdge {
+        from: NodeId,
+        to: NodeId,
+    },
+    /// A client or a server is linked to something else than a drone.
+    NonDroneLink(NodeId, NodeId),
+    /// Clients must be linked to one or two drones.
+    ClientDrones {
+        client: NodeId,
+        drones: Vec<NodeId>,
+    },
+    /// Servers must be linked to at least two drones.
+    ServerDrones {
+        server: NodeId,
+        drones: Vec<NodeId>,
+    },
+    /// The network is split, every component is listed.
+    Disconnected(Vec<Vec<NodeId>>),
+}
+
+impl Violation {
+    /// Violations that prevent the harness from spawning the network at all.
+    pub fn is_malformed(&self) -> bool {
+        matches!(
+            self,
+            Violation::DuplicateId(_) | Violation::UnknownNode { .. } | Violation::SelfLoop(_)
+        )
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::DuplicateId(id) => write!(f, "id {} is declared more than once", id),
+            Violation::UnknownNode { edge, id } => write!(
+                f,
+                "edge {} <-> {} refers to the unknown node {}",
+                edge.0, edge.1, id
+            ),
+            Violation::SelfLoop(id) => write!(f, "node {} is linked to itself", id),
+            Violation::DuplicateEdge(a, b) => {
+                write!(f, "edge {} <-> {} is declared more than once", a, b)
+            }
+            Violation::AsymmetricEdge { from, to } => write!(
+                f,
+                "node {} lists {} as neighbour, but {} does not list {}",
+                from, to, to, from
+            ),
+            Violation::NonDroneLink(a, b) => write!(
+                f,
+                "edge {} <-> {} links a client or a server to a node that is not a drone",
+                a, b
+            ),
+            Violation::ClientDrones { client, drones } => write!(
+                f,
+                "client {} must be linked to one or two drones, it is linked to {:?}",
+                client, drones
+            ),
+            Violation::ServerDrones { server, drones } => write!(
+                f,
+                "server {} must be linked to at least two drones, it is linked to {:?}",
+                server, drones
+            ),
+            Violation::Disconnected(components) => write!(
+                f,
+                "the network is split into {} components: {:?}",
+                components.len(),
+                components
+            ),
+        }
+    }
+}
+
+/// Every violation found in a topology.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError(pub Vec<Violation>);
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the topology breaks {} rule(s):", self.0.len())?;
+        for violation in &self.0 {
+            write!(f, "\n  - {}", violation)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+impl TopologyBuilder {
+    /// Checks the topology against the rules of the protocol, reporting every violation.
+    /// The toy topologies of the generic tests do not need to pass, e.g. their servers are often linked to a single drone.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        into_result(self.violations())
+    }
+
+    /// Same as [`TopologyBuilder::validate`], panicking with every violation.
+    pub fn validated(self) -> Self {
+        if let Err(err) = self.validate() {
+            panic!("{}", err);
+        }
+        self
+    }
+
+    pub fn violations(&self) -> Vec<Violation> {
+        let mut violations = Vec::new();
+
+        let mut roles = HashMap::new();
+        for &(id, role) in self.nodes() {
+            if roles.insert(id, role).is_some() {
+                violations.push(Violation::DuplicateId(id));
+            }
+        }
+        let is_drone = |id: &NodeId| matches!(roles.get(id), Some(NodeRole::Drone { .. }));
+
+        let mut seen_edges = HashSet::new();
+        for &(a, b) in self.edges() {
+            if a == b {
+                violations.push(Violation::SelfLoop(a));
+                continue;
+            }
+            let unknown: Vec<NodeId> = [a, b]
+                .into_iter()
+                .filter(|id| !roles.contains_key(id))
+                .collect();
+            if !unknown.is_empty() {
+                for id in unknown {
+                    violations.push(Violation::UnknownNode { edge: (a, b), id });
+                }
+                continue;
+            }
+            if !seen_edges.insert((a.min(b), a.max(b))) {
+                violations.push(Violation::DuplicateEdge(a, b));
+            }
+            if !is_drone(&a) && !is_drone(&b) {
+                violations.push(Violation::NonDroneLink(a, b));
+            }
+        }
+
+        let drones_of = |id: NodeId| -> Vec<NodeId> {
+            let mut drones: Vec<NodeId> = self
+                .neighbours(id)
+                .into_iter()
+                .filter(|n| is_drone(n))
+                .collect();
+            drones.sort_unstable();
+            drones.dedup();
+            drones
+        };
+        for &(id, role) in self.nodes() {
+            match role {
+                NodeRole::Client => {
+                    let drones = drones_of(id);
+                    if drones.is_empty() || drones.len() > 2 {
+                        violations.push(Violation::ClientDrones { client: id, drones });
+                    }
+                }
+                NodeRole::Server => {
+                    let drones = drones_of(id);
+                    if drones.len() < 2 {
+                        violations.push(Violation::ServerDrones { server: id, drones });
+                    }
+                }
+                NodeRole::Drone { .. } => {}
+            }
+        }
+        dedup(&mut violations);
+
+        let components = self.components(is_drone);
+        if components.len() > 1 {
+            violations.push(Violation::Disconnected(components));
+        }
+        violations
+    }
+
+    /// Groups the nodes by the part of the drone network they can reach.
+    /// Packets are only routed through drones, so clients and servers never join two components.
+    fn components(&self, is_drone: impl Fn(&NodeId) -> bool) -> Vec<Vec<NodeId>> {
+        let mut component: HashMap<NodeId, usize> = HashMap::new();
+        let mut components: Vec<Vec<NodeId>> = Vec::new();
+
+        for &(start, _) in self.nodes() {
+            if !is_drone(&start) || component.contains_key(&start) {
+                continue;
+            }
+            let index = components.len();
+            let mut members = vec![start];
+            component.insert(start, index);
+            let mut next = 0;
+            while next < members.len() {
+                for n in self.neighbours(members[next]) {
+                    if is_drone(&n) && !component.contains_key(&n) {
+                        component.insert(n, index);
+                        members.push(n);
+                    }
+                }
+                next += 1;
+            }
+            components.push(members);
+        }
+
+        for &(id, _) in self.nodes() {
+            if is_drone(&id) || component.contains_key(&id) {
+                continue;
+            }
+            match self.neighbours(id).iter().find(|n| is_drone(n)) {
+                Some(drone) => {
+                    let index = component[drone];
+                    component.insert(id, index);
+                    components[index].push(id);
+                }
+                None => {
+                    component.insert(id, components.len());
+                    components.push(vec![id]);
+                }
+            }
+        }
+
+        for members in &mut components {
+            members.sort_unstable();
+        }
+        components.sort();
+        components
+    }
+}
+
+/// Checks a network-initialization file: the rules of [`TopologyBuilder::validate`], plus the links listed by only
+/// one of their endpoints or listed twice by the same node.
+pub fn validate_config(config: &Config) -> Result<(), ValidationError> {
+    let listed: HashMap<NodeId, &Vec<NodeId>> = config
+        .drone
+        .iter()
+        .map(|drone| (drone.id, &drone.connected_node_ids))
+        .chain(
+            config
+                .client
+                .iter()
+                .map(|client| (client.id, &client.connected_drone_ids)),
+        )
+        .chain(
+            config
+                .server
+                .iter()
+                .map(|server| (server.id, &server.connected_drone_ids)),
+        )
+        .collect();
+
+    let mut violations = Vec::new();
+    let mut ids: Vec<&NodeId> = listed.keys().collect();
+    ids.sort_unstable();
+    for from in ids {
+        for (i, to) in listed[from].iter().enumerate() {
+            if listed[from][..i].contains(to) {
+                violations.push(Violation::DuplicateEdge(*from, *to));
+                continue;
+            }
+            let asymmetric = listed
+                .get(to)
+                .is_some_and(|connected| !connected.contains(from));
+            if asymmetric {
+                violations.push(Violation::AsymmetricEdge {
+                    from: *from,
+                    to: *to,
+                });
+            }
+        }
+    }
+    violations.extend(TopologyBuilder::from_config(config).violations());
+    into_result(violations)
+}
+
+fn into_result(violations: Vec<Violation>) -> Result<(), ValidationError> {
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationError(violations))
+    }
+}
+
+/// Removes repeated violations, keeping the first occurrence.
+fn dedup(violations: &mut Vec<Violation>) {
+    let mut unique: Vec<Violation> = Vec::with_capacity(violations.len());
+    for violation in violations.drain(..) {
+        if !unique.contains(&violation) {
+            unique.push(violation);
+        }
+    }
+    *violations = unique;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ### Network Topology
+    /// C(1) <-> D(11) <-> D(12)
+    /// S(21) <-> D(11), S(21) <-> D(12)
+    fn valid() -> TopologyBuilder {
+        TopologyBuilder::new()
+            .client(1)
+            .drone(11, 0.0)
+            .drone(12, 0.0)
+            .server(21)
+            .edge(1, 11)
+            .edge(11, 12)
+            .edge(21, 11)
+            .edge(21, 12)
+    }
+
+    /// Same network as [`valid`], as a network-initialization file.
+    const VALID_TOML: &str = r#"
+        [[drone]]
+        id = 11
+        connected_node_ids = [1, 12, 21]
+        pdr = 0.0
+
+        [[drone]]
+        id = 12
+        connected_node_ids = [11, 21]
+        pdr = 0.0
+
+        [[client]]
+        id = 1
+        connected_drone_ids = [11]
+
+        [[server]]
+        id = 21
+        connected_drone_ids = [11, 12]
+    "#;
+
+    fn config(toml: &str) -> Config {
+        toml::from_str(toml).unwrap()
+    }
+
+    fn config_violations(toml: &str) -> Vec<Violation> {
+        match validate_config(&config(toml)) {
+            Ok(()) => Vec::new(),
+            Err(ValidationError(violations)) => violations,
+        }
+    }
+
+    #[test]
+    fn valid_topology() {
+        assert_eq!(valid().violations(), vec![]);
+        assert_eq!(config_violations(VALID_TOML), vec![]);
+    }
+
+    #[test]
+    fn duplicate_id() {
+        let topology = valid().drone(12, 0.5);
+        assert_eq!(topology.violations(), vec![Violation::DuplicateId(12)]);
+    }
+
+    #[test]
+    fn unknown_node() {
+        let topology = valid().edge(12, 13);
+        assert_eq!(
+            topology.violations(),
+            vec![Violation::UnknownNode {
+                edge: (12, 13),
+                id: 13
+            }]
+        );
+
+        let toml = VALID_TOML.replace("[11, 21]\n", "[11, 21, 13]\n");
+        assert_eq!(
+            config_violations(&toml),
+            vec![Violation::UnknownNode {
+                edge: (12, 13),
+                id: 13
+            }]
+        );
+    }
+
+    #[test]
+    fn self_loop() {
+        let topology = valid().edge(12, 12);
+        assert_eq!(topology.violations(), vec![Violation::SelfLoop(12)]);
+    }
+
+    #[test]
+    fn duplicate_edge() {
+        let topology = valid().edge(12, 11);
+        assert_eq!(
+            topology.violations(),
+            vec![Violation::DuplicateEdge(12, 11)]
+        );
+
+        let toml = VALID_TOML.replace("[11, 21]\n", "[11, 21, 11]\n");
+        assert_eq!(
+            config_violations(&toml),
+            vec![Violation::DuplicateEdge(12, 11)]
+        );
+    }
+
+    #[test]
+    fn asymmetric_edge() {
+        let toml = VALID_TOML.replace("[11, 21]\n", "[21]\n");
+        assert_eq!(
+            config_violations(&toml),
+            vec![Violation::AsymmetricEdge { from: 11, to: 12 }]
+        );
+    }
+
+    #[test]
+    fn non_drone_link() {
+        let topology = valid().client(2).edge(2, 11).edge(2, 21);
+        assert_eq!(topology.violations(), vec![Violation::NonDroneLink(2, 21)]);
+    }
+
+    #[test]
+    fn client_drones() {
+        let topology = valid()
+            .drone(13, 0.0)
+            .edge(13, 12)
+            .client(2)
+            .edge(2, 11)
+            .edge(2, 12)
+            .edge(2, 13);
+        assert_eq!(
+            topology.violations(),
+            vec![Violation::ClientDrones {
+                client: 2,
+                drones: vec![11, 12, 13]
+            }]
+        );
+    }
+
+    #[test]
+    fn server_drones() {
+        let topology = valid().server(22).edge(22, 12);
+        assert_eq!(
+            topology.violations(),
+            vec![Violation::ServerDrones {
+                server: 22,
+                drones: vec![12]
+            }]
+        );
+    }
+
+    #[test]
+    fn disconnected() {
+        // A second network, C(2) <-> D(13) <-> D(14), and a client linked to nothing
+        let topology = valid()
+            .client(2)
+            .drone(13, 0.0)
+            .drone(14, 0.0)
+            .edge(2, 13)
+            .edge(13, 14)
+            .client(3);
+        assert_eq!(
+            topology.violations(),
+            vec![
+                Violation::ClientDrones {
+                    client: 3,
+                    drones: vec![]
+                },
+                Violation::Disconnected(vec![vec![1, 11, 12, 21], vec![2, 13, 14], vec![3]]),
+            ]
+        );
+    }
+
+    #[test]
+    fn every_violation_is_reported() {
+        let toml = VALID_TOML
+            .replace("[1, 12, 21]", "[1, 12, 21, 11]")
+            .replace("[11, 21]\n", "[21, 21]\n");
+        assert_eq!(
+            config_violations(&toml),
+            vec![
+                Violation::AsymmetricEdge { from: 11, to: 12 },
+                Violation::DuplicateEdge(12, 21),
+                Violation::SelfLoop(11),
+            ]
+        );
+    }
+}
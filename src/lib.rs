@@ -1,4 +1,6 @@
 mod drone;
 mod custom_macro;
+mod harness;
 
 pub use drone::*;
+pub use harness::*;